@@ -1,6 +1,10 @@
+use crate::config::Profile;
+use crate::history;
 use reqwest::{Client, RequestBuilder, Response};
 use std::env;
+use std::io::Write;
 
+#[derive(Clone)]
 enum Auth {
     ApiKey(String),
     Basic { username: String, password: String },
@@ -16,27 +20,45 @@ impl EsClient {
     pub fn new() -> Result<Self, String> {
         let base_url = env::var("ELASTICSEARCH_URL").map_err(|_| "ELASTICSEARCH_URL not set")?;
 
-        let auth = if let Ok(key) = env::var("ELASTIC_API_KEY").or_else(|_| env::var("ELASTICSEARCH_API_KEY")) {
-            Auth::ApiKey(key)
-        } else if let (Ok(username), Ok(password)) = (env::var("ELASTIC_USERNAME"), env::var("ELASTIC_PASSWORD")) {
-            Auth::Basic { username, password }
-        } else {
-            return Err(
-                "No authentication configured. Set ELASTIC_API_KEY (or ELASTICSEARCH_API_KEY), \
-                 or set both ELASTIC_USERNAME and ELASTIC_PASSWORD."
-                    .to_string(),
-            );
-        };
+        Ok(Self {
+            client: Client::new(),
+            base_url,
+            auth: env_auth()?,
+        })
+    }
 
-        let client = Client::new();
+    /// Client for a connection profile, with the credentials of the environment
+    /// when the profile has none
+    pub fn from_profile(profile: &Profile) -> Result<Self, String> {
+        let auth = match (&profile.api_key, &profile.username, &profile.password) {
+            (Some(key), _, _) => Auth::ApiKey(key.clone()),
+            (None, Some(username), Some(password)) => Auth::Basic {
+                username: username.clone(),
+                password: password.clone(),
+            },
+            _ => env_auth()?,
+        };
 
         Ok(Self {
-            client,
-            base_url,
+            client: Client::new(),
+            base_url: profile.url.clone(),
             auth,
         })
     }
 
+    /// The same connection to another cluster URL
+    pub fn with_url(&self, url: &str) -> Self {
+        Self {
+            client: self.client.clone(),
+            base_url: url.trim_end_matches('/').to_string(),
+            auth: self.auth.clone(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
     fn apply_auth(&self, builder: RequestBuilder) -> RequestBuilder {
         match &self.auth {
             Auth::ApiKey(key) => builder.header("Authorization", format!("ApiKey {}", key)),
@@ -61,4 +83,78 @@ impl EsClient {
             .await
             .map_err(|e| e.to_string())
     }
+
+    pub async fn put(&self, path: &str, body: &str) -> Result<Response, String> {
+        let url = format!("{}{}", self.base_url, path);
+        self.apply_auth(self.client.put(&url))
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<Response, String> {
+        let url = format!("{}{}", self.base_url, path);
+        self.apply_auth(self.client.delete(&url))
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// POST a newline-delimited JSON body (bulk-style APIs such as _msearch)
+    pub async fn post_ndjson(&self, path: &str, body: &str) -> Result<Response, String> {
+        let url = format!("{}{}", self.base_url, path);
+        self.apply_auth(self.client.post(&url))
+            .header("Content-Type", "application/x-ndjson")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Credentials from the environment: an API key, or a username and password
+fn env_auth() -> Result<Auth, String> {
+    if let Ok(key) = env::var("ELASTIC_API_KEY").or_else(|_| env::var("ELASTICSEARCH_API_KEY")) {
+        Ok(Auth::ApiKey(key))
+    } else if let (Ok(username), Ok(password)) =
+        (env::var("ELASTIC_USERNAME"), env::var("ELASTIC_PASSWORD"))
+    {
+        Ok(Auth::Basic { username, password })
+    } else {
+        Err(
+            "No authentication configured. Set ELASTIC_API_KEY (or ELASTICSEARCH_API_KEY), \
+             or set both ELASTIC_USERNAME and ELASTIC_PASSWORD."
+                .to_string(),
+        )
+    }
+}
+
+/// Read a response body, turning non-success statuses into errors
+pub async fn read_body(response: Response) -> Result<String, String> {
+    if !response.status().is_success() {
+        return Err(format!(
+            "HTTP {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        ));
+    }
+
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    history::observe(&body);
+    Ok(body)
+}
+
+/// Copy a response body to stdout as it arrives (non-JSON formats such as CSV or Arrow)
+pub async fn stream_body(mut response: Response) -> Result<(), String> {
+    if !response.status().is_success() {
+        return read_body(response).await.map(|_| ());
+    }
+
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        stdout.write_all(&chunk).map_err(|e| e.to_string())?;
+    }
+    stdout.flush().map_err(|e| e.to_string())
 }
@@ -0,0 +1,253 @@
+use crate::client::{read_body, EsClient};
+use crate::format::{format_csv, format_table};
+use crate::query::parse_optional_query;
+use crate::timerange::{interval_param, TimeArgs};
+use serde_json::{json, Map, Value};
+
+/// Options for the aggregation builder
+pub struct AggOptions<'a> {
+    pub index: &'a str,
+    pub query: Option<&'a str>,
+    pub by: &'a [String],
+    pub metrics: &'a [String],
+    pub time: &'a TimeArgs,
+    pub timestamp_field: &'a str,
+    pub csv: bool,
+}
+
+/// A bucket level: `field`, `terms:field[:size]`, `date_histogram:field:interval`
+/// or `histogram:field:interval`
+struct Grouping {
+    field: String,
+    agg: Value,
+}
+
+/// A metric: `avg|sum|min|max|count|cardinality|pNN:field`
+struct Metric {
+    label: String,
+    agg: Value,
+}
+
+pub async fn run(opts: AggOptions<'_>, human: bool) -> Result<(), String> {
+    let groupings = opts
+        .by
+        .iter()
+        .map(|spec| parse_grouping(spec, &opts.time.time_zone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let metrics = opts
+        .metrics
+        .iter()
+        .map(|spec| parse_metric(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+    let range = opts.time.resolve()?;
+    let query = parse_optional_query(opts.query)?;
+
+    let client = EsClient::new()?;
+    let path = format!("/{}/_search", opts.index);
+
+    let body = json!({
+        "size": 0,
+        "track_total_hits": true,
+        "query": range.apply(query, opts.timestamp_field),
+        "aggs": build_aggs(&groupings, &metrics)
+    });
+
+    let response = client.post(&path, &body.to_string()).await?;
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    // One row per bucket path: grouping keys, doc count, then metrics
+    let mut columns: Vec<String> = groupings.iter().map(|g| g.field.clone()).collect();
+    columns.push("count".to_string());
+    columns.extend(metrics.iter().map(|m| m.label.clone()));
+
+    let mut rows = Vec::new();
+    let aggregations = value.get("aggregations").cloned().unwrap_or(json!({}));
+    if groupings.is_empty() {
+        let total = value
+            .pointer("/hits/total/value")
+            .cloned()
+            .unwrap_or(Value::Null);
+        let mut row = vec![total];
+        row.extend(metric_values(&aggregations, &metrics));
+        rows.push(row);
+    } else {
+        collect_rows(
+            &aggregations,
+            0,
+            groupings.len(),
+            &metrics,
+            &mut Vec::new(),
+            &mut rows,
+        );
+    }
+
+    if opts.csv {
+        print!("{}", format_csv(&columns, &rows));
+    } else if human {
+        println!("{}", format_table(&columns, &rows));
+    } else {
+        let items: Vec<Value> = rows
+            .iter()
+            .map(|row| {
+                let item: Map<String, Value> =
+                    columns.iter().cloned().zip(row.iter().cloned()).collect();
+                Value::Object(item)
+            })
+            .collect();
+        println!("{}", Value::Array(items));
+    }
+    Ok(())
+}
+
+fn parse_grouping(spec: &str, time_zone: &str) -> Result<Grouping, String> {
+    let parts: Vec<&str> = spec.split(':').collect();
+    let invalid = || {
+        format!(
+            "Invalid --by \"{}\": expected field, terms:field[:size], \
+             date_histogram:field:interval or histogram:field:interval",
+            spec
+        )
+    };
+
+    let (field, agg) = match parts.as_slice() {
+        [field] | ["terms", field] => (field, json!({ "terms": { "field": field, "size": 10 } })),
+        ["terms", field, size] => {
+            let size: u64 = size.parse().map_err(|_| invalid())?;
+            (field, json!({ "terms": { "field": field, "size": size } }))
+        }
+        ["date_histogram", field, interval] => {
+            let (interval_key, interval) = interval_param(interval)?;
+            (
+                field,
+                json!({
+                    "date_histogram": {
+                        "field": field,
+                        interval_key: interval,
+                        "time_zone": time_zone
+                    }
+                }),
+            )
+        }
+        ["histogram", field, interval] => {
+            let interval: f64 = interval.parse().map_err(|_| invalid())?;
+            (
+                field,
+                json!({ "histogram": { "field": field, "interval": interval } }),
+            )
+        }
+        _ => return Err(invalid()),
+    };
+    if field.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Grouping {
+        field: field.to_string(),
+        agg,
+    })
+}
+
+fn parse_metric(spec: &str) -> Result<Metric, String> {
+    let invalid = || {
+        format!(
+            "Invalid --metric \"{}\": expected avg|sum|min|max|count|cardinality|pNN:field",
+            spec
+        )
+    };
+    let (kind, field) = spec.split_once(':').ok_or_else(invalid)?;
+    if field.is_empty() {
+        return Err(invalid());
+    }
+
+    let agg = match kind {
+        "avg" | "sum" | "min" | "max" | "cardinality" => json!({ kind: { "field": field } }),
+        "count" => json!({ "value_count": { "field": field } }),
+        _ => {
+            let percent: f64 = kind
+                .strip_prefix('p')
+                .and_then(|p| p.parse().ok())
+                .filter(|p| (0.0..=100.0).contains(p))
+                .ok_or_else(invalid)?;
+            json!({ "percentiles": { "field": field, "percents": [percent] } })
+        }
+    };
+
+    Ok(Metric {
+        label: format!("{}({})", kind, field),
+        agg,
+    })
+}
+
+/// Nest each grouping into the previous one, metrics go in the innermost level
+fn build_aggs(groupings: &[Grouping], metrics: &[Metric]) -> Value {
+    let mut aggs = Map::new();
+    for (i, metric) in metrics.iter().enumerate() {
+        aggs.insert(format!("m{}", i), metric.agg.clone());
+    }
+
+    for (level, grouping) in groupings.iter().enumerate().rev() {
+        let mut agg = grouping.agg.clone();
+        if !aggs.is_empty() {
+            agg["aggs"] = Value::Object(aggs);
+        }
+        aggs = Map::new();
+        aggs.insert(format!("by{}", level), agg);
+    }
+
+    Value::Object(aggs)
+}
+
+fn collect_rows(
+    node: &Value,
+    level: usize,
+    depth: usize,
+    metrics: &[Metric],
+    keys: &mut Vec<Value>,
+    rows: &mut Vec<Vec<Value>>,
+) {
+    let Some(buckets) = node
+        .get(format!("by{}", level))
+        .and_then(|agg| agg.get("buckets"))
+        .and_then(|b| b.as_array())
+    else {
+        return;
+    };
+
+    for bucket in buckets {
+        let key = bucket
+            .get("key_as_string")
+            .or_else(|| bucket.get("key"))
+            .cloned()
+            .unwrap_or(Value::Null);
+        keys.push(key);
+
+        if level + 1 == depth {
+            let mut row = keys.clone();
+            row.push(bucket.get("doc_count").cloned().unwrap_or(Value::Null));
+            row.extend(metric_values(bucket, metrics));
+            rows.push(row);
+        } else {
+            collect_rows(bucket, level + 1, depth, metrics, keys, rows);
+        }
+
+        keys.pop();
+    }
+}
+
+fn metric_values(node: &Value, metrics: &[Metric]) -> Vec<Value> {
+    (0..metrics.len())
+        .map(|i| {
+            let agg = node.get(format!("m{}", i));
+            agg.and_then(|a| a.get("value"))
+                .or_else(|| {
+                    // Percentiles: a single requested percent, keyed like "95.0"
+                    agg.and_then(|a| a.get("values"))
+                        .and_then(|v| v.as_object())
+                        .and_then(|v| v.values().next())
+                })
+                .cloned()
+                .unwrap_or(Value::Null)
+        })
+        .collect()
+}
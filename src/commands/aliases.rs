@@ -1,4 +1,4 @@
-use crate::client::EsClient;
+use crate::client::{read_body, EsClient};
 use crate::format::format_output;
 
 pub async fn run(pattern: Option<&str>, human: bool) -> Result<(), String> {
@@ -11,15 +11,7 @@ pub async fn run(pattern: Option<&str>, human: bool) -> Result<(), String> {
 
     let response = client.get(&path).await?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
     println!("{}", format_output(&body, human));
     Ok(())
 }
@@ -0,0 +1,281 @@
+use crate::client::{read_body, EsClient};
+use crate::format::{format_eql, format_output};
+use serde_json::Value;
+use std::io::{IsTerminal, Write};
+use std::time::Instant;
+
+/// How long each request waits for completion before reporting progress
+const WAIT: &str = "2s";
+
+/// The async APIs: async search, async ES|QL and EQL
+#[derive(Clone, Copy)]
+pub enum AsyncKind {
+    Search,
+    Esql,
+    Eql,
+}
+
+impl AsyncKind {
+    /// Kind of an ID given on the command line (default: async search)
+    pub fn from_flags(esql: bool, eql: bool) -> Self {
+        if esql {
+            AsyncKind::Esql
+        } else if eql {
+            AsyncKind::Eql
+        } else {
+            AsyncKind::Search
+        }
+    }
+
+    fn path(self, id: &str) -> String {
+        match self {
+            AsyncKind::Search => format!("/_async_search/{}", id),
+            AsyncKind::Esql => format!("/_query/async/{}", id),
+            AsyncKind::Eql => format!("/_eql/search/{}", id),
+        }
+    }
+
+    /// Command to pick up the results later
+    fn resume_hint(self, id: &str) -> String {
+        match self {
+            AsyncKind::Search => format!("es-cli async get {}", id),
+            AsyncKind::Esql => format!("es-cli async get --esql {}", id),
+            AsyncKind::Eql => format!("es-cli async get --eql {}", id),
+        }
+    }
+}
+
+/// Run a search through _async_search and return the search response once complete
+pub async fn search(client: &EsClient, index: &str, body: &Value) -> Result<String, String> {
+    let path = format!(
+        "/{}/_async_search?wait_for_completion_timeout={}&keep_on_completion=true",
+        index, WAIT
+    );
+    let response = client.post(&path, &body.to_string()).await?;
+    let value = parse(&read_body(response).await?)?;
+    let value = finish(client, AsyncKind::Search, value).await?;
+    Ok(result(AsyncKind::Search, &value))
+}
+
+/// Run an ES|QL query through _query/async and return the response once complete
+pub async fn esql(client: &EsClient, body: &Value) -> Result<String, String> {
+    let mut body = body.clone();
+    body["wait_for_completion_timeout"] = Value::from(WAIT);
+    body["keep_on_completion"] = Value::from(true);
+    let response = client.post("/_query/async", &body.to_string()).await?;
+    let value = parse(&read_body(response).await?)?;
+    let value = finish(client, AsyncKind::Esql, value).await?;
+    Ok(result(AsyncKind::Esql, &value))
+}
+
+/// Run an EQL search, polling if it doesn't complete right away, and return the response
+pub async fn eql(client: &EsClient, index: &str, body: &Value) -> Result<String, String> {
+    let path = format!(
+        "/{}/_eql/search?wait_for_completion_timeout={}&keep_on_completion=true",
+        index, WAIT
+    );
+    let response = client.post(&path, &body.to_string()).await?;
+    let value = parse(&read_body(response).await?)?;
+    let value = finish(client, AsyncKind::Eql, value).await?;
+    Ok(result(AsyncKind::Eql, &value))
+}
+
+/// Wait for a submitted query, then delete the stored result since it was printed
+async fn finish(client: &EsClient, kind: AsyncKind, value: Value) -> Result<Value, String> {
+    let Some(id) = value.get("id").and_then(|i| i.as_str()).map(String::from) else {
+        return Ok(value);
+    };
+    let value = if is_running(&value) {
+        wait(client, kind, &id, &value).await?
+    } else {
+        value
+    };
+    let _ = client.delete(&kind.path(&id)).await;
+    Ok(value)
+}
+
+/// Poll until the query completes. On Ctrl-C, stop polling and give the ID to resume with.
+async fn wait(
+    client: &EsClient,
+    kind: AsyncKind,
+    id: &str,
+    first: &Value,
+) -> Result<Value, String> {
+    let started = Instant::now();
+    let show_progress = std::io::stderr().is_terminal();
+    let mut value = first.clone();
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+
+    while is_running(&value) {
+        if show_progress {
+            eprint!("\r{}", progress(kind, &value, started));
+            let _ = std::io::stderr().flush();
+        }
+
+        let path = format!("{}?wait_for_completion_timeout={}", kind.path(id), WAIT);
+        let response = tokio::select! {
+            response = client.get(&path) => response?,
+            _ = &mut interrupted => {
+                if show_progress {
+                    eprintln!();
+                }
+                return Err(format!(
+                    "Interrupted, the query keeps running on the cluster (ID: {}). \
+                     Resume with: {}",
+                    id,
+                    kind.resume_hint(id)
+                ));
+            }
+        };
+        value = parse(&read_body(response).await?)?;
+    }
+
+    if show_progress {
+        // Clear the progress line
+        eprint!("\r{}\r", " ".repeat(70));
+    }
+    Ok(value)
+}
+
+fn parse(body: &str) -> Result<Value, String> {
+    let value: Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    if let Some(error) = value.get("error") {
+        let reason = error
+            .get("reason")
+            .and_then(|r| r.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| error.to_string());
+        return Err(reason);
+    }
+    Ok(value)
+}
+
+fn is_running(value: &Value) -> bool {
+    value
+        .get("is_running")
+        .and_then(|r| r.as_bool())
+        .unwrap_or(false)
+}
+
+fn progress(kind: AsyncKind, value: &Value, started: Instant) -> String {
+    let elapsed = started.elapsed().as_secs();
+    match kind {
+        AsyncKind::Search => {
+            let shards = value.pointer("/response/_shards");
+            let count = |key: &str| {
+                shards
+                    .and_then(|s| s.get(key))
+                    .and_then(|c| c.as_u64())
+                    .unwrap_or(0)
+            };
+            let done = count("successful") + count("skipped") + count("failed");
+            format!(
+                "Running... {}/{} shards done, {}s elapsed",
+                done,
+                count("total"),
+                elapsed
+            )
+        }
+        AsyncKind::Esql | AsyncKind::Eql => format!("Running... {}s elapsed", elapsed),
+    }
+}
+
+/// The search response of an async search, or the ES|QL and EQL responses as is
+fn result(kind: AsyncKind, value: &Value) -> String {
+    match kind {
+        AsyncKind::Search => value.get("response").unwrap_or(value).to_string(),
+        AsyncKind::Esql | AsyncKind::Eql => value.to_string(),
+    }
+}
+
+/// Fetch the results of an async query, waiting for it if it is still running
+pub async fn get(id: &str, kind: AsyncKind, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+
+    let path = format!("{}?wait_for_completion_timeout={}", kind.path(id), WAIT);
+    let response = client.get(&path).await?;
+    let value = parse(&read_body(response).await?)?;
+    let value = if is_running(&value) {
+        wait(&client, kind, id, &value).await?
+    } else {
+        value
+    };
+
+    let body = result(kind, &value);
+    if let (AsyncKind::Eql, true) = (kind, human) {
+        println!("{}", format_eql(&body, "@timestamp"));
+    } else {
+        println!("{}", format_output(&body, human));
+    }
+    Ok(())
+}
+
+/// Show whether an async query is still running, without its results
+pub async fn status(id: &str, kind: AsyncKind, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+
+    // ES|QL has no status API: fetch without waiting and keep the status fields
+    let path = match kind {
+        AsyncKind::Search => format!("/_async_search/status/{}", id),
+        AsyncKind::Eql => format!("/_eql/search/status/{}", id),
+        AsyncKind::Esql => format!("{}?wait_for_completion_timeout=0s", kind.path(id)),
+    };
+    let response = client.get(&path).await?;
+    let mut value = parse(&read_body(response).await?)?;
+    if let (AsyncKind::Esql, Some(obj)) = (kind, value.as_object_mut()) {
+        obj.retain(|key, _| matches!(key.as_str(), "id" | "is_running" | "is_partial" | "took"));
+    }
+
+    if human {
+        println!("{}", format_status_human(&value));
+    } else {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+/// Delete an async query, cancelling it if it is still running
+pub async fn delete(id: &str, kind: AsyncKind, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let response = client.delete(&kind.path(id)).await?;
+    let body = read_body(response).await?;
+
+    if human {
+        println!("Deleted {}", id);
+    } else {
+        println!("{}", body);
+    }
+    Ok(())
+}
+
+fn format_status_human(value: &Value) -> String {
+    let mut output = String::new();
+    let running = is_running(value);
+    output.push_str(&format!(
+        "{:<20} {}\n",
+        "Status:",
+        if running { "running" } else { "completed" }
+    ));
+    if let Some(partial) = value.get("is_partial").and_then(|p| p.as_bool()) {
+        output.push_str(&format!("{:<20} {}\n", "Partial:", partial));
+    }
+    if let Some(shards) = value.get("_shards") {
+        let count = |key: &str| shards.get(key).and_then(|c| c.as_u64()).unwrap_or(0);
+        output.push_str(&format!(
+            "{:<20} {} total, {} successful, {} skipped, {} failed\n",
+            "Shards:",
+            count("total"),
+            count("successful"),
+            count("skipped"),
+            count("failed")
+        ));
+    }
+    if let Some(status) = value.get("completion_status") {
+        output.push_str(&format!("{:<20} {}\n", "HTTP status:", status));
+    }
+    if let Some(took) = value.get("took").and_then(|t| t.as_u64()) {
+        output.push_str(&format!("{:<20} {}ms\n", "Took:", took));
+    }
+    output
+}
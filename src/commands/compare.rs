@@ -0,0 +1,405 @@
+use crate::client::{read_body, EsClient};
+use crate::format::{format_table, format_value};
+use crate::query::parse_optional_query;
+use crate::timerange::{shift_back, TimeRange};
+use chrono::{DateTime, Duration, FixedOffset, Local, SecondsFormat};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Options for the period-over-period comparison
+pub struct CompareOptions<'a> {
+    pub index: &'a str,
+    pub query: Option<&'a str>,
+    pub window: &'a str,
+    pub vs: &'a str,
+    pub fields: &'a [String],
+    pub size: usize,
+    pub buckets: usize,
+    pub timestamp_field: &'a str,
+}
+
+/// What one window returned: total, counts per time bucket, top terms per field
+struct WindowResult {
+    total: u64,
+    histogram: Vec<u64>,
+    terms: Vec<Vec<(Value, u64)>>,
+}
+
+pub async fn run(opts: CompareOptions<'_>, human: bool) -> Result<(), String> {
+    let filter = parse_optional_query(opts.query)?;
+
+    // The current window ends now, the previous one `vs` earlier
+    let now = Local::now().fixed_offset();
+    let current = TimeRange {
+        from: Some(shift_back(now, opts.window)?),
+        to: Some(now),
+    };
+    let previous_to = shift_back(now, opts.vs)?;
+    let previous = TimeRange {
+        from: Some(shift_back(previous_to, opts.window)?),
+        to: Some(previous_to),
+    };
+    let windows = [&current, &previous];
+
+    let client = EsClient::new()?;
+
+    let mut request = String::new();
+    for window in windows {
+        let body = window_body(&opts, &filter, window);
+        request.push_str(&json!({ "index": opts.index }).to_string());
+        request.push('\n');
+        request.push_str(&body.to_string());
+        request.push('\n');
+    }
+    let responses = send(&client, &request, windows.len()).await?;
+    let mut results: Vec<WindowResult> = responses
+        .iter()
+        .map(|r| parse_window(r, opts.fields.len()))
+        .collect();
+
+    // Terms in the top list of one window only: fetch their exact count in the
+    // other window, so that "new" and "gone" mean a count of zero
+    let union: Vec<Vec<Value>> = (0..opts.fields.len())
+        .map(|i| union_keys(&results[0].terms[i], &results[1].terms[i]))
+        .collect();
+    let incomplete = union
+        .iter()
+        .enumerate()
+        .any(|(i, keys)| results.iter().any(|r| r.terms[i].len() < keys.len()));
+    if incomplete {
+        let mut request = String::new();
+        for window in windows {
+            let mut aggs = Map::new();
+            for (i, (field, keys)) in opts.fields.iter().zip(&union).enumerate() {
+                aggs.insert(
+                    format!("f{}", i),
+                    json!({
+                        "filter": { "terms": { field.as_str(): keys } },
+                        "aggs": { "values": { "terms": { "field": field, "size": keys.len().max(1) } } }
+                    }),
+                );
+            }
+            let body = json!({
+                "size": 0,
+                "query": window.apply(filter.clone(), opts.timestamp_field),
+                "aggs": aggs
+            });
+            request.push_str(&json!({ "index": opts.index }).to_string());
+            request.push('\n');
+            request.push_str(&body.to_string());
+            request.push('\n');
+        }
+        let responses = send(&client, &request, windows.len()).await?;
+        for (result, response) in results.iter_mut().zip(&responses) {
+            for (i, terms) in result.terms.iter_mut().enumerate() {
+                *terms = parse_terms(response.pointer(&format!("/aggregations/f{}/values", i)));
+            }
+        }
+    }
+
+    let output = build_output(&opts, &current, &previous, &results, &union);
+    if human {
+        println!("{}", format_compare_human(&output, opts.vs));
+    } else {
+        println!("{}", output);
+    }
+    Ok(())
+}
+
+fn window_body(opts: &CompareOptions, filter: &Value, window: &TimeRange) -> Value {
+    let (from, to) = (window.from.unwrap(), window.to.unwrap());
+
+    // Explicit ranges from the window start, so that bucket i of both windows
+    // covers the same offset
+    let step = (to - from).num_milliseconds() / opts.buckets.max(1) as i64;
+    let ranges: Vec<Value> = (0..opts.buckets.max(1) as i64)
+        .map(|i| {
+            json!({
+                "from": timestamp(from + Duration::milliseconds(step * i)),
+                "to": timestamp(from + Duration::milliseconds(step * (i + 1)))
+            })
+        })
+        .collect();
+
+    let mut aggs = Map::new();
+    aggs.insert(
+        "histogram".to_string(),
+        json!({ "date_range": { "field": opts.timestamp_field, "ranges": ranges } }),
+    );
+    for (i, field) in opts.fields.iter().enumerate() {
+        aggs.insert(
+            format!("f{}", i),
+            json!({ "terms": { "field": field, "size": opts.size } }),
+        );
+    }
+
+    json!({
+        "size": 0,
+        "track_total_hits": true,
+        "query": window.apply(filter.clone(), opts.timestamp_field),
+        "aggs": aggs
+    })
+}
+
+fn timestamp(time: DateTime<FixedOffset>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+/// Run an `_msearch` of `searches` searches, failing on any search error
+async fn send(client: &EsClient, request: &str, searches: usize) -> Result<Vec<Value>, String> {
+    let response = client.post_ndjson("/_msearch", request).await?;
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    let responses = value
+        .get("responses")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .ok_or_else(|| "Invalid _msearch response: missing responses".to_string())?;
+    if responses.len() != searches {
+        return Err(format!(
+            "Invalid _msearch response: {} responses for {} searches",
+            responses.len(),
+            searches
+        ));
+    }
+
+    for response in &responses {
+        if let Some(error) = response.get("error") {
+            let reason = error
+                .get("reason")
+                .and_then(|r| r.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| error.to_string());
+            return Err(reason);
+        }
+    }
+    Ok(responses)
+}
+
+fn parse_window(response: &Value, fields: usize) -> WindowResult {
+    let total = response
+        .pointer("/hits/total/value")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(0);
+    let histogram = response
+        .pointer("/aggregations/histogram/buckets")
+        .and_then(|b| b.as_array())
+        .map(|buckets| {
+            buckets
+                .iter()
+                .map(|b| b.get("doc_count").and_then(|c| c.as_u64()).unwrap_or(0))
+                .collect()
+        })
+        .unwrap_or_default();
+    let terms = (0..fields)
+        .map(|i| parse_terms(response.pointer(&format!("/aggregations/f{}", i))))
+        .collect();
+
+    WindowResult {
+        total,
+        histogram,
+        terms,
+    }
+}
+
+fn parse_terms(agg: Option<&Value>) -> Vec<(Value, u64)> {
+    agg.and_then(|a| a.get("buckets"))
+        .and_then(|b| b.as_array())
+        .map(|buckets| {
+            buckets
+                .iter()
+                .map(|b| {
+                    let key = b
+                        .get("key_as_string")
+                        .or_else(|| b.get("key"))
+                        .cloned()
+                        .unwrap_or(Value::Null);
+                    let count = b.get("doc_count").and_then(|c| c.as_u64()).unwrap_or(0);
+                    (key, count)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Current top terms first, then the ones only in the previous top terms
+fn union_keys(current: &[(Value, u64)], previous: &[(Value, u64)]) -> Vec<Value> {
+    let mut keys: Vec<Value> = current.iter().map(|(k, _)| k.clone()).collect();
+    for (key, _) in previous {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+    keys
+}
+
+fn compare_row(current: u64, previous: u64) -> Map<String, Value> {
+    let mut row = Map::new();
+    row.insert("current".to_string(), json!(current));
+    row.insert("previous".to_string(), json!(previous));
+    row.insert("delta".to_string(), json!(current as i64 - previous as i64));
+    row.insert(
+        "ratio".to_string(),
+        if previous > 0 {
+            json!(current as f64 / previous as f64)
+        } else {
+            Value::Null
+        },
+    );
+    row
+}
+
+fn build_output(
+    opts: &CompareOptions,
+    current: &TimeRange,
+    previous: &TimeRange,
+    results: &[WindowResult],
+    union: &[Vec<Value>],
+) -> Value {
+    let window = |range: &TimeRange| {
+        json!({
+            "from": range.from.map(timestamp),
+            "to": range.to.map(timestamp)
+        })
+    };
+
+    let mut count = compare_row(results[0].total, results[1].total);
+    count.insert("current_window".to_string(), window(current));
+    count.insert("previous_window".to_string(), window(previous));
+
+    let step = (current.to.unwrap() - current.from.unwrap()).num_milliseconds()
+        / opts.buckets.max(1) as i64;
+    let histogram: Vec<Value> = (0..opts.buckets.max(1))
+        .map(|i| {
+            let mut row = Map::new();
+            row.insert(
+                "time".to_string(),
+                json!(timestamp(
+                    current.from.unwrap() + Duration::milliseconds(step * i as i64)
+                )),
+            );
+            row.extend(compare_row(
+                results[0].histogram.get(i).copied().unwrap_or(0),
+                results[1].histogram.get(i).copied().unwrap_or(0),
+            ));
+            Value::Object(row)
+        })
+        .collect();
+
+    let mut fields = Map::new();
+    for (i, field) in opts.fields.iter().enumerate() {
+        let counts: Vec<HashMap<String, u64>> = results
+            .iter()
+            .map(|r| {
+                r.terms[i]
+                    .iter()
+                    .map(|(k, c)| (k.to_string(), *c))
+                    .collect()
+            })
+            .collect();
+        let mut rows: Vec<Map<String, Value>> = union[i]
+            .iter()
+            .map(|key| {
+                let current = counts[0].get(&key.to_string()).copied().unwrap_or(0);
+                let previous = counts[1].get(&key.to_string()).copied().unwrap_or(0);
+                let mut row = Map::new();
+                row.insert("value".to_string(), key.clone());
+                row.extend(compare_row(current, previous));
+                let status = match (current, previous) {
+                    (0, p) if p > 0 => json!("gone"),
+                    (c, 0) if c > 0 => json!("new"),
+                    _ => Value::Null,
+                };
+                row.insert("status".to_string(), status);
+                row
+            })
+            .collect();
+        rows.sort_by_key(|row| {
+            std::cmp::Reverse((
+                row["current"].as_u64().unwrap_or(0),
+                row["previous"].as_u64().unwrap_or(0),
+            ))
+        });
+        fields.insert(
+            field.clone(),
+            Value::Array(rows.into_iter().map(Value::Object).collect()),
+        );
+    }
+
+    json!({
+        "count": count,
+        "histogram": histogram,
+        "fields": fields
+    })
+}
+
+fn format_compare_human(output: &Value, vs: &str) -> String {
+    let mut text = String::new();
+    let count = &output["count"];
+    text.push_str(&format!(
+        "Current:  {} → {}\n",
+        format_value(&count["current_window"]["from"]),
+        format_value(&count["current_window"]["to"])
+    ));
+    text.push_str(&format!(
+        "Previous: {} → {} ({} earlier)\n\n",
+        format_value(&count["previous_window"]["from"]),
+        format_value(&count["previous_window"]["to"]),
+        vs
+    ));
+
+    let columns = |first: &str| -> Vec<String> {
+        [first, "current", "previous", "delta", "ratio"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect()
+    };
+    let row = |label: Value, item: &Value| -> Vec<Value> {
+        vec![
+            label,
+            item["current"].clone(),
+            item["previous"].clone(),
+            item["delta"].clone(),
+            item["ratio"].clone(),
+        ]
+    };
+
+    text.push_str(&format_table(&columns(""), &[row(json!("Count"), count)]));
+    text.push('\n');
+
+    if let Some(histogram) = output["histogram"].as_array() {
+        let rows: Vec<Vec<Value>> = histogram
+            .iter()
+            .map(|item| row(item["time"].clone(), item))
+            .collect();
+        text.push_str(&format_table(&columns("time"), &rows));
+        text.push('\n');
+    }
+
+    if let Some(fields) = output["fields"].as_object() {
+        for (field, items) in fields {
+            let mut columns = columns(field);
+            columns.push("status".to_string());
+            let rows: Vec<Vec<Value>> = items
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| {
+                            let mut r = row(item["value"].clone(), item);
+                            r.push(match &item["status"] {
+                                Value::Null => json!(""),
+                                status => status.clone(),
+                            });
+                            r
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            text.push_str(&format_table(&columns, &rows));
+            text.push('\n');
+        }
+    }
+
+    text
+}
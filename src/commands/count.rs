@@ -1,29 +1,28 @@
-use crate::client::EsClient;
+use crate::client::{read_body, EsClient};
+use crate::query::parse_optional_query;
+use crate::timerange::TimeArgs;
+use serde_json::json;
+
+pub async fn run(
+    index: &str,
+    query: Option<&str>,
+    time: &TimeArgs,
+    timestamp_field: &str,
+    human: bool,
+) -> Result<(), String> {
+    let range = time.resolve()?;
+    let query = parse_optional_query(query)?;
+
+    // Restrict the query to the time range, if any
+    let body = json!({ "query": range.apply(query, timestamp_field) });
 
-pub async fn run(index: &str, query: Option<&str>, human: bool) -> Result<(), String> {
     let client = EsClient::new()?;
 
     let path = format!("/{}/_count", index);
 
-    let response = match query {
-        Some(q) => {
-            // Validate JSON
-            serde_json::from_str::<serde_json::Value>(q)
-                .map_err(|e| format!("Invalid JSON query: {}", e))?;
-            client.post(&path, q).await?
-        }
-        None => client.post(&path, r#"{"query":{"match_all":{}}}"#).await?,
-    };
-
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
+    let response = client.post(&path, &body.to_string()).await?;
 
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
 
     if human {
         // Parse and show just the count
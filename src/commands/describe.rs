@@ -0,0 +1,360 @@
+use crate::client::{read_body, EsClient};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+pub async fn run(index: &str, timestamp_field: &str, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+
+    let timestamp_body = json!({
+        "size": 0,
+        "track_total_hits": false,
+        "aggs": {
+            "min": { "min": { "field": timestamp_field } },
+            "max": { "max": { "field": timestamp_field } }
+        }
+    });
+    let cat_path = format!(
+        "/_cat/indices/{}?format=json&bytes=b&h=index,health,status,pri,rep,store.size,creation.date.string",
+        index
+    );
+    let settings_path = format!(
+        "/{}/_settings/index.mapping.total_fields.limit?include_defaults=true&flat_settings=true",
+        index
+    );
+    let count_path = format!("/{}/_count", index);
+    let resolve_path = format!("/_resolve/index/{}", index);
+    let ilm_path = format!("/{}/_ilm/explain", index);
+    let mapping_path = format!("/{}/_mapping", index);
+    let search_path = format!("/{}/_search", index);
+
+    let (cat, count, settings, resolve, ilm, mapping, timestamps) = tokio::join!(
+        get_json(&client, &cat_path),
+        get_json(&client, &count_path),
+        get_json(&client, &settings_path),
+        get_json(&client, &resolve_path),
+        get_json(&client, &ilm_path),
+        get_json(&client, &mapping_path),
+        post_json(&client, &search_path, &timestamp_body),
+    );
+
+    // The index listing is required; the other parts are left out when they fail
+    // (e.g., no ILM on serverless, or missing privileges)
+    let cat = cat?;
+    let rows = cat.as_array().cloned().unwrap_or_default();
+    if rows.is_empty() {
+        return Err(format!("No index matches {}", index));
+    }
+
+    let mut output = json!({ "index": index });
+    summarize_indices(&rows, &mut output);
+    if let Ok(count) = count {
+        output["docs"] = count.get("count").cloned().unwrap_or(Value::Null);
+    }
+    if let Ok(resolve) = resolve {
+        summarize_resolve(&resolve, &mut output);
+    }
+    if let Ok(ilm) = ilm {
+        output["ilm"] = summarize_ilm(&ilm);
+    }
+    output["fields"] = json!({
+        "count": mapping.ok().map(|m| max_field_count(&m)),
+        "limit": settings.ok().and_then(|s| min_field_limit(&s))
+    });
+    let timestamp = |key: &str| {
+        timestamps.as_ref().ok().and_then(|t| {
+            let agg = t.pointer(&format!("/aggregations/{}", key))?;
+            agg.get("value_as_string").or(agg.get("value")).cloned()
+        })
+    };
+    output["timestamp"] = json!({
+        "field": timestamp_field,
+        "min": timestamp("min"),
+        "max": timestamp("max")
+    });
+
+    if human {
+        println!("{}", format_describe_human(&output));
+    } else {
+        println!("{}", output);
+    }
+    Ok(())
+}
+
+async fn get_json(client: &EsClient, path: &str) -> Result<Value, String> {
+    let response = client.get(path).await?;
+    serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())
+}
+
+async fn post_json(client: &EsClient, path: &str, body: &Value) -> Result<Value, String> {
+    let response = client.post(path, &body.to_string()).await?;
+    serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())
+}
+
+/// Totals over the matching indices: worst health, shards, size, oldest creation date
+fn summarize_indices(rows: &[Value], output: &mut Value) {
+    let text = |row: &Value, key: &str| {
+        row.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let number = |row: &Value, key: &str| text(row, key).parse::<u64>().unwrap_or(0);
+
+    let mut names: Vec<String> = rows.iter().map(|r| text(r, "index")).collect();
+    names.sort();
+    let health = ["red", "yellow", "green"]
+        .into_iter()
+        .find(|h| rows.iter().any(|r| text(r, "health") == *h))
+        .unwrap_or("unknown");
+    let statuses: BTreeSet<String> = rows.iter().map(|r| text(r, "status")).collect();
+    // Creation dates are ISO 8601 strings in UTC, so the smallest is the oldest
+    let created = rows
+        .iter()
+        .map(|r| text(r, "creation.date.string"))
+        .filter(|d| !d.is_empty())
+        .min();
+
+    output["indices"] = json!(names);
+    output["health"] = json!(health);
+    output["status"] = json!(statuses.into_iter().collect::<Vec<_>>().join(","));
+    output["store_size_bytes"] = json!(rows.iter().map(|r| number(r, "store.size")).sum::<u64>());
+    output["primary_shards"] = json!(rows.iter().map(|r| number(r, "pri")).sum::<u64>());
+    output["replicas"] = json!(rows.iter().map(|r| number(r, "rep")).max().unwrap_or(0));
+    output["created"] = json!(created);
+}
+
+/// Aliases and data streams the target is, or belongs to
+fn summarize_resolve(resolve: &Value, output: &mut Value) {
+    let mut aliases = BTreeSet::new();
+    let mut data_streams = BTreeSet::new();
+    let list = |key: &str| {
+        resolve
+            .get(key)
+            .and_then(|l| l.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    for index in list("indices") {
+        if let Some(names) = index.get("aliases").and_then(|a| a.as_array()) {
+            aliases.extend(names.iter().filter_map(|n| n.as_str()).map(String::from));
+        }
+        if let Some(name) = index.get("data_stream").and_then(|d| d.as_str()) {
+            data_streams.insert(name.to_string());
+        }
+    }
+    for alias in list("aliases") {
+        if let Some(name) = alias.get("name").and_then(|n| n.as_str()) {
+            aliases.insert(name.to_string());
+        }
+    }
+    for data_stream in list("data_streams") {
+        if let Some(name) = data_stream.get("name").and_then(|n| n.as_str()) {
+            data_streams.insert(name.to_string());
+        }
+    }
+
+    output["aliases"] = json!(aliases);
+    output["data_streams"] = json!(data_streams);
+}
+
+/// Policy and phase of each ILM-managed index
+fn summarize_ilm(ilm: &Value) -> Value {
+    let mut managed: Vec<Value> = ilm
+        .get("indices")
+        .and_then(|i| i.as_object())
+        .map(|indices| {
+            indices
+                .iter()
+                .filter(|(_, explain)| explain["managed"] == json!(true))
+                .map(|(name, explain)| {
+                    json!({
+                        "index": name,
+                        "policy": explain.get("policy"),
+                        "phase": explain.get("phase")
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    managed.sort_by(|a, b| a["index"].as_str().cmp(&b["index"].as_str()));
+    json!(managed)
+}
+
+/// Largest field count among the indices, counted like index.mapping.total_fields.limit:
+/// object fields, leaf fields, multi-fields and runtime fields
+fn max_field_count(mapping: &Value) -> u64 {
+    mapping
+        .as_object()
+        .map(|indices| {
+            indices
+                .values()
+                .map(|index| {
+                    let mappings = &index["mappings"];
+                    let runtime = mappings
+                        .get("runtime")
+                        .and_then(|r| r.as_object())
+                        .map_or(0, |r| r.len() as u64);
+                    count_fields(&mappings["properties"]) + runtime
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+}
+
+fn count_fields(properties: &Value) -> u64 {
+    properties
+        .as_object()
+        .map(|fields| {
+            fields
+                .values()
+                .map(|field| {
+                    1 + count_fields(&field["properties"])
+                        + field
+                            .get("fields")
+                            .and_then(|f| f.as_object())
+                            .map_or(0, |f| f.len() as u64)
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Smallest total fields limit among the indices, set explicitly or by default
+fn min_field_limit(settings: &Value) -> Option<u64> {
+    const KEY: &str = "index.mapping.total_fields.limit";
+    settings
+        .as_object()?
+        .values()
+        .filter_map(|index| {
+            index
+                .pointer(&format!("/settings/{}", KEY))
+                .or_else(|| index.pointer(&format!("/defaults/{}", KEY)))
+                .and_then(|limit| limit.as_str())
+                .and_then(|limit| limit.parse::<u64>().ok())
+        })
+        .min()
+}
+
+fn format_describe_human(output: &Value) -> String {
+    let mut text = String::new();
+    let mut line = |label: &str, value: String| {
+        text.push_str(&format!("{:<20} {}\n", format!("{}:", label), value));
+    };
+    let or_dash = |value: &Value| match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) if s.is_empty() => "-".to_string(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    let join = |value: &Value| {
+        let names: Vec<&str> = value
+            .as_array()
+            .map(|a| a.iter().filter_map(|n| n.as_str()).collect())
+            .unwrap_or_default();
+        if names.is_empty() {
+            "-".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+
+    line("Index", or_dash(&output["index"]));
+    let indices = output["indices"].as_array().map_or(0, |i| i.len());
+    if indices > 1 {
+        line(
+            "Indices",
+            format!("{} ({})", indices, join(&output["indices"])),
+        );
+    }
+    line("Health", or_dash(&output["health"]));
+    line("Status", or_dash(&output["status"]));
+    line("Documents", or_dash(&output["docs"]));
+    line(
+        "Store size",
+        format_bytes(output["store_size_bytes"].as_u64().unwrap_or(0)),
+    );
+    line(
+        "Shards",
+        format!(
+            "{} primary, {} replica(s)",
+            or_dash(&output["primary_shards"]),
+            or_dash(&output["replicas"])
+        ),
+    );
+    line("Created", or_dash(&output["created"]));
+    line("Aliases", join(&output["aliases"]));
+    line("Data stream", join(&output["data_streams"]));
+
+    let ilm = match output["ilm"].as_array() {
+        None => "-".to_string(),
+        Some(managed) if managed.is_empty() => "not managed".to_string(),
+        Some(managed) => {
+            let policies: BTreeSet<String> =
+                managed.iter().map(|m| or_dash(&m["policy"])).collect();
+            let phases: Vec<String> = managed.iter().map(|m| or_dash(&m["phase"])).collect();
+            let mut counts: Vec<(String, usize)> = Vec::new();
+            for phase in phases {
+                match counts.iter_mut().find(|(p, _)| *p == phase) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((phase, 1)),
+                }
+            }
+            let phases: Vec<String> = if counts.len() == 1 && managed.len() == 1 {
+                vec![counts[0].0.clone()]
+            } else {
+                counts
+                    .iter()
+                    .map(|(p, c)| format!("{}: {}", p, c))
+                    .collect()
+            };
+            format!(
+                "{} (policy: {})",
+                phases.join(", "),
+                policies.into_iter().collect::<Vec<_>>().join(", ")
+            )
+        }
+    };
+    line("ILM phase", ilm);
+
+    let fields = &output["fields"];
+    let field_count = match (fields["count"].as_u64(), fields["limit"].as_u64()) {
+        (Some(count), Some(limit)) => format!(
+            "{} of {} ({:.0}%)",
+            count,
+            limit,
+            count as f64 * 100.0 / limit.max(1) as f64
+        ),
+        (Some(count), None) => count.to_string(),
+        _ => "-".to_string(),
+    };
+    line("Fields", field_count);
+
+    let timestamp = &output["timestamp"];
+    line(
+        &format!("Oldest {}", or_dash(&timestamp["field"])),
+        or_dash(&timestamp["min"]),
+    );
+    line(
+        &format!("Newest {}", or_dash(&timestamp["field"])),
+        or_dash(&timestamp["max"]),
+    );
+
+    text
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["b", "kb", "mb", "gb", "tb"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
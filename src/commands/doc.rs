@@ -0,0 +1,168 @@
+use crate::client::{read_body, EsClient};
+use crate::format::format_source;
+use serde_json::{json, Value};
+use std::io::Read;
+
+/// Options shared by `doc get` and `doc mget`
+pub struct DocOptions<'a> {
+    pub includes: Option<&'a str>,
+    pub excludes: Option<&'a str>,
+    pub routing: Option<&'a str>,
+    pub raw: bool,
+}
+
+impl DocOptions<'_> {
+    /// Query string for _source filtering and routing
+    fn params(&self) -> String {
+        let params: Vec<String> = [
+            ("_source_includes", self.includes),
+            ("_source_excludes", self.excludes),
+            ("routing", self.routing),
+        ]
+        .iter()
+        .filter_map(|(name, value)| value.map(|v| format!("{}={}", name, encode(v))))
+        .collect();
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
+/// Print one document (GET /<index>/_doc/<id>)
+pub async fn get(index: &str, id: &str, opts: DocOptions<'_>, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let path = format!("/{}/_doc/{}{}", index, encode(id), opts.params());
+    let response = client.get(&path).await?;
+
+    // A missing document is a 404 with "found": false; a missing index is an error body
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        let value: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+        if value.get("found").and_then(|f| f.as_bool()) == Some(false) {
+            return Err(format!("Document {} not found in {}", id, index));
+        }
+        return Err(format!("HTTP 404 Not Found: {}", body));
+    }
+
+    let body = read_body(response).await?;
+    let doc: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    print_docs(&[doc], &opts, human, false);
+    Ok(())
+}
+
+/// Print the documents listed on stdin as `index/id` lines (POST /_mget)
+pub async fn mget(opts: DocOptions<'_>, human: bool) -> Result<(), String> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| format!("Cannot read stdin: {}", e))?;
+
+    let mut docs = Vec::new();
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (index, id) = line
+            .split_once('/')
+            .filter(|(index, id)| !index.is_empty() && !id.is_empty())
+            .ok_or_else(|| format!("Line {}: expected index/id, got '{}'", n + 1, line))?;
+        docs.push(json!({ "_index": index, "_id": id }));
+    }
+    if docs.is_empty() {
+        return Err("No documents given on stdin (one index/id per line)".to_string());
+    }
+
+    let client = EsClient::new()?;
+    let path = format!("/_mget{}", opts.params());
+    let response = client
+        .post(&path, &json!({ "docs": docs }).to_string())
+        .await?;
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    let docs = value
+        .get("docs")
+        .and_then(|d| d.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    if !human && !opts.raw {
+        println!("{}", body);
+        return Ok(());
+    }
+    print_docs(&docs, &opts, human, true);
+    Ok(())
+}
+
+/// Human output: a header per document and its source. Raw output: the sources
+/// only, as one JSON object per line (missing documents are skipped).
+fn print_docs(docs: &[Value], opts: &DocOptions, human: bool, several: bool) {
+    for doc in docs {
+        let found = doc.get("found").and_then(|f| f.as_bool()).unwrap_or(false);
+        let source = doc.get("_source").cloned().unwrap_or(json!({}));
+
+        if !human {
+            if opts.raw {
+                if found {
+                    println!("{}", source);
+                }
+            } else {
+                println!("{}", doc);
+            }
+            continue;
+        }
+
+        if several {
+            let header = format!(
+                "--- {}/{} ",
+                doc.get("_index").and_then(|i| i.as_str()).unwrap_or(""),
+                doc.get("_id").and_then(|i| i.as_str()).unwrap_or("")
+            );
+            if let Some(error) = doc.get("error") {
+                let reason = error
+                    .get("reason")
+                    .and_then(|r| r.as_str())
+                    .map(String::from)
+                    .unwrap_or_else(|| error.to_string());
+                println!("{}(error: {}) ---\n", header, reason);
+                continue;
+            }
+            if !found {
+                println!("{}(not found) ---\n", header);
+                continue;
+            }
+            if !opts.raw {
+                println!("{}---", header);
+            }
+        } else if !opts.raw {
+            println!(
+                "{:<14} {}\n{:<14} {}\n{:<14} {}\n",
+                "Index:",
+                doc.get("_index").and_then(|i| i.as_str()).unwrap_or(""),
+                "ID:",
+                doc.get("_id").and_then(|i| i.as_str()).unwrap_or(""),
+                "Version:",
+                doc.get("_version")
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+            );
+        }
+        println!("{}", format_source(&source, 0));
+    }
+}
+
+/// Percent-encode a path segment or query value (IDs may contain '/', '#', '?', ...)
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b',' | b'*' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+use crate::client::{read_body, EsClient};
+use crate::commands::async_query;
+use crate::format::{format_eql, format_output};
+use serde_json::json;
+
+/// Options for EQL searches
+pub struct EqlOptions<'a> {
+    pub index: &'a str,
+    pub query: &'a str,
+    pub timestamp_field: &'a str,
+    pub tiebreaker: Option<&'a str>,
+    pub size: usize,
+    pub run_async: bool,
+}
+
+pub async fn run(opts: EqlOptions<'_>, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let mut body = json!({
+        "query": opts.query,
+        "timestamp_field": opts.timestamp_field,
+        "size": opts.size
+    });
+    if let Some(tiebreaker) = opts.tiebreaker {
+        body["tiebreaker_field"] = json!(tiebreaker);
+    }
+
+    let body = if opts.run_async {
+        async_query::eql(&client, opts.index, &body).await?
+    } else {
+        let path = format!("/{}/_eql/search", opts.index);
+        let response = client.post(&path, &body.to_string()).await?;
+        read_body(response).await?
+    };
+
+    if human {
+        println!("{}", format_eql(&body, opts.timestamp_field));
+    } else {
+        println!("{}", format_output(&body, false));
+    }
+    Ok(())
+}
@@ -1,21 +1,99 @@
-use crate::client::EsClient;
-use crate::format::format_output;
-use serde_json::json;
+use crate::client::{read_body, stream_body, EsClient};
+use crate::commands::async_query;
+use crate::format::{format_output, format_profile};
+use crate::query::{parse_params, parse_query};
+use serde_json::{json, Value};
 
-pub async fn run(query: &str, human: bool) -> Result<(), String> {
+/// Options for ES|QL queries
+pub struct EsqlOptions<'a> {
+    pub query: &'a str,
+    pub params: &'a [String],
+    pub filter: Option<&'a str>,
+    pub columnar: bool,
+    pub locale: Option<&'a str>,
+    pub time_zone: Option<&'a str>,
+    pub format: Option<&'a str>,
+    pub profile: bool,
+    pub run_async: bool,
+}
+
+pub async fn run(opts: EsqlOptions<'_>, human: bool) -> Result<(), String> {
     let client = EsClient::new()?;
-    let body = json!({ "query": query }).to_string();
-    let response = client.post("/_query", &body).await?;
+    let mut body = json!({ "query": opts.query });
+
+    // Named parameters for ?name placeholders: [{"name": value}, ...]
+    if !opts.params.is_empty() {
+        let params: Vec<Value> = parse_params(opts.params)?
+            .into_iter()
+            .map(|(name, value)| json!({ name: value }))
+            .collect();
+        body["params"] = json!(params);
+    }
+    if let Some(filter) = opts.filter {
+        body["filter"] = parse_query(filter)?;
+    }
+    if opts.columnar {
+        body["columnar"] = json!(true);
+    }
+    if let Some(locale) = opts.locale {
+        body["locale"] = json!(locale);
+    }
+    if let Some(time_zone) = opts.time_zone {
+        body["time_zone"] = json!(time_zone);
+    }
+    if opts.profile {
+        body["profile"] = json!(true);
+    }
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
+    // Server-side formats are printed as they arrive, without re-rendering
+    if let Some(format) = opts.format {
+        let path = format!("/_query?format={}", format);
+        let response = client.post(&path, &body.to_string()).await?;
+        return stream_body(response).await;
     }
 
-    let body = response.text().await.map_err(|e| e.to_string())?;
-    println!("{}", format_output(&body, human));
+    let body = if opts.run_async {
+        async_query::esql(&client, &body).await?
+    } else {
+        let response = client.post("/_query", &body.to_string()).await?;
+        read_body(response).await?
+    };
+    if opts.columnar && human {
+        println!("{}", format_output(&to_rows(&body), human));
+    } else {
+        println!("{}", format_output(&body, human));
+    }
+    if opts.profile && human {
+        println!("{}", format_profile(&body));
+    }
     Ok(())
 }
+
+/// Turn a columnar response (one array per column) back into rows for display
+fn to_rows(json: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(json) else {
+        return json.to_string();
+    };
+    let Some(columns) = value.get("values").and_then(|v| v.as_array()) else {
+        return json.to_string();
+    };
+
+    let len = columns
+        .iter()
+        .filter_map(|c| c.as_array())
+        .map(|c| c.len())
+        .max()
+        .unwrap_or(0);
+    let rows: Vec<Value> = (0..len)
+        .map(|i| {
+            Value::Array(
+                columns
+                    .iter()
+                    .map(|c| c.get(i).cloned().unwrap_or(Value::Null))
+                    .collect(),
+            )
+        })
+        .collect();
+    value["values"] = Value::Array(rows);
+    value.to_string()
+}
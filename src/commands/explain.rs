@@ -0,0 +1,74 @@
+use crate::client::{read_body, EsClient};
+use crate::query::parse_query;
+use serde_json::{json, Value};
+
+pub async fn run(index: &str, id: &str, query: &str, human: bool) -> Result<(), String> {
+    let query = parse_query(query)?;
+
+    let client = EsClient::new()?;
+    let path = format!("/{}/_explain/{}", index, id);
+    let response = client
+        .post(&path, &json!({ "query": query }).to_string())
+        .await?;
+
+    let body = read_body(response).await?;
+
+    if human {
+        println!("{}", format_explain_human(&body));
+    } else {
+        println!("{}", body);
+    }
+    Ok(())
+}
+
+fn format_explain_human(json: &str) -> String {
+    let value: Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(_) => return json.to_string(),
+    };
+
+    let mut output = String::new();
+
+    let matched = value
+        .get("matched")
+        .and_then(|m| m.as_bool())
+        .unwrap_or(false);
+    output.push_str(&format!(
+        "Matched: {}\n",
+        if matched { "yes" } else { "no" }
+    ));
+
+    if let Some(explanation) = value.get("explanation") {
+        output.push('\n');
+        format_explanation(explanation, "", "", &mut output);
+    }
+
+    output
+}
+
+/// Render an explanation node and its details as a tree
+fn format_explanation(node: &Value, prefix: &str, child_prefix: &str, output: &mut String) {
+    let score = node.get("value").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let description = node
+        .get("description")
+        .and_then(|d| d.as_str())
+        .unwrap_or("-");
+    output.push_str(&format!("{}{:.4}  {}\n", prefix, score, description));
+
+    if let Some(details) = node.get("details").and_then(|d| d.as_array()) {
+        for (i, detail) in details.iter().enumerate() {
+            let last = i + 1 == details.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            format_explanation(
+                detail,
+                &format!("{}{}", child_prefix, branch),
+                &format!("{}{}", child_prefix, indent),
+                output,
+            );
+        }
+    }
+}
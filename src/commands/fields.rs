@@ -1,21 +1,28 @@
-use crate::client::EsClient;
-use crate::format::format_output;
+use crate::client::{read_body, EsClient};
+use crate::format::{format_output, format_table, truncate};
+use crate::query::sampler_probability;
+use serde_json::{json, Map, Value};
 
-pub async fn run(index: &str, human: bool) -> Result<(), String> {
+/// Options for listing fields
+pub struct FieldsOptions<'a> {
+    pub index: &'a str,
+    pub caps: bool,
+    pub coverage: bool,
+    pub sample_size: u64,
+}
+
+pub async fn run(opts: FieldsOptions<'_>, human: bool) -> Result<(), String> {
+    if opts.caps || opts.coverage {
+        return run_caps(opts, human).await;
+    }
+
+    let index = opts.index;
     let client = EsClient::new()?;
 
     let path = format!("/{}/_mapping", index);
     let response = client.get(&path).await?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
 
     // For human output, we extract and flatten the fields
     if human {
@@ -26,6 +33,242 @@ pub async fn run(index: &str, human: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Field capabilities across all matching indices, optionally with the share of
+/// documents containing each field
+async fn run_caps(opts: FieldsOptions<'_>, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+
+    let path = format!("/{}/_field_caps?fields=*", opts.index);
+    let response = client.get(&path).await?;
+    let value: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+    let all_indices: Vec<Value> = value
+        .get("indices")
+        .and_then(|i| i.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut fields: Vec<Map<String, Value>> = value
+        .get("fields")
+        .and_then(|f| f.as_object())
+        .map(|caps| {
+            caps.iter()
+                .filter_map(|(name, types)| field_caps(name, types, &all_indices))
+                .collect()
+        })
+        .unwrap_or_default();
+    fields.sort_by(|a, b| a["field"].as_str().cmp(&b["field"].as_str()));
+
+    let mut output = json!({ "fields": [] });
+    if opts.coverage {
+        let names: Vec<&str> = fields.iter().filter_map(|f| f["field"].as_str()).collect();
+        let (documents, counts) = coverage(&client, opts.index, &names, opts.sample_size).await?;
+        for (field, count) in fields.iter_mut().zip(counts) {
+            let percent = if documents > 0 {
+                count as f64 * 100.0 / documents as f64
+            } else {
+                0.0
+            };
+            field.insert("coverage".to_string(), json!(percent));
+        }
+        output["documents"] = json!(documents);
+    }
+    output["fields"] = Value::Array(fields.into_iter().map(Value::Object).collect());
+
+    if human {
+        println!("{}", format_caps_human(&output, opts.caps, opts.coverage));
+    } else {
+        println!("{}", output);
+    }
+    Ok(())
+}
+
+/// Summary of one _field_caps entry, None for metadata fields and objects.
+/// A field with several types is a conflict: each type lists the indices using it.
+fn field_caps(name: &str, types: &Value, all_indices: &[Value]) -> Option<Map<String, Value>> {
+    let types = types.as_object()?;
+    let types: Vec<(&String, &Value)> = types
+        .iter()
+        .filter(|(kind, caps)| {
+            !matches!(kind.as_str(), "object" | "nested")
+                && !caps
+                    .get("metadata_field")
+                    .and_then(|m| m.as_bool())
+                    .unwrap_or(false)
+        })
+        .collect();
+    if types.is_empty() {
+        return None;
+    }
+
+    let flag = |key: &str| types.iter().all(|(_, caps)| caps[key] == json!(true));
+    let indices_without = |key: &str| -> Vec<Value> {
+        types
+            .iter()
+            .filter_map(|(_, caps)| caps.get(key).and_then(|i| i.as_array()))
+            .flatten()
+            .cloned()
+            .collect()
+    };
+
+    let mut field = Map::new();
+    field.insert("field".to_string(), json!(name));
+    field.insert(
+        "types".to_string(),
+        json!(types.iter().map(|(kind, _)| kind).collect::<Vec<_>>()),
+    );
+    field.insert("searchable".to_string(), json!(flag("searchable")));
+    field.insert("aggregatable".to_string(), json!(flag("aggregatable")));
+
+    if types.len() > 1 {
+        // With a single type the response has no index list: it is all the indices
+        let conflicts: Map<String, Value> = types
+            .iter()
+            .map(|(kind, caps)| {
+                let indices = caps
+                    .get("indices")
+                    .cloned()
+                    .unwrap_or_else(|| json!(all_indices));
+                (kind.to_string(), indices)
+            })
+            .collect();
+        field.insert("conflicts".to_string(), Value::Object(conflicts));
+    }
+    for key in ["non_searchable_indices", "non_aggregatable_indices"] {
+        let indices = indices_without(key);
+        if !indices.is_empty() {
+            field.insert(key.to_string(), json!(indices));
+        }
+    }
+    Some(field)
+}
+
+/// Number of documents looked at and, for each field, how many of them contain it.
+/// Large indices are sampled with the random sampler to about `sample_size` documents.
+async fn coverage(
+    client: &EsClient,
+    index: &str,
+    fields: &[&str],
+    sample_size: u64,
+) -> Result<(u64, Vec<u64>), String> {
+    let mut aggs = Map::new();
+    for (i, field) in fields.iter().enumerate() {
+        aggs.insert(
+            format!("f{}", i),
+            json!({ "filter": { "exists": { "field": field } } }),
+        );
+    }
+
+    let response = client.get(&format!("/{}/_count", index)).await?;
+    let value: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+    let total = value.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+
+    let path = format!("/{}/_search", index);
+    let (body, prefix) = if let Some(probability) = sampler_probability(sample_size, total) {
+        let sampler = json!({
+            "random_sampler": { "probability": probability },
+            "aggs": aggs
+        });
+        (
+            json!({ "size": 0, "aggs": { "sample": sampler } }),
+            "/aggregations/sample",
+        )
+    } else {
+        (json!({ "size": 0, "aggs": aggs }), "/aggregations")
+    };
+    let response = client.post(&path, &body.to_string()).await?;
+    let value: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+
+    let documents = if prefix == "/aggregations" {
+        total
+    } else {
+        value
+            .pointer(&format!("{}/doc_count", prefix))
+            .and_then(|c| c.as_u64())
+            .unwrap_or(0)
+    };
+    let counts = (0..fields.len())
+        .map(|i| {
+            value
+                .pointer(&format!("{}/f{}/doc_count", prefix, i))
+                .and_then(|c| c.as_u64())
+                .unwrap_or(0)
+        })
+        .collect();
+    Ok((documents, counts))
+}
+
+fn format_caps_human(output: &Value, caps: bool, coverage: bool) -> String {
+    let fields = output["fields"].as_array().cloned().unwrap_or_default();
+
+    let mut columns = vec!["field".to_string(), "type".to_string()];
+    if caps {
+        columns.push("searchable".to_string());
+        columns.push("aggregatable".to_string());
+    }
+    if coverage {
+        columns.push("coverage".to_string());
+    }
+
+    // A flag true on some indices only is "partial"
+    let flag = |field: &Value, key: &str, without: &str| -> Value {
+        match (field[key].as_bool(), field.get(without)) {
+            (Some(true), _) => json!("yes"),
+            (_, Some(_)) => json!("partial"),
+            _ => json!("no"),
+        }
+    };
+
+    let rows: Vec<Vec<Value>> = fields
+        .iter()
+        .map(|field| {
+            let types: Vec<&str> = field["types"]
+                .as_array()
+                .map(|t| t.iter().filter_map(|t| t.as_str()).collect())
+                .unwrap_or_default();
+            let mut row = vec![field["field"].clone(), json!(types.join(" | "))];
+            if caps {
+                row.push(flag(field, "searchable", "non_searchable_indices"));
+                row.push(flag(field, "aggregatable", "non_aggregatable_indices"));
+            }
+            if coverage {
+                let percent = field["coverage"].as_f64().unwrap_or(0.0);
+                row.push(json!(format!("{:.1}%", percent)));
+            }
+            row
+        })
+        .collect();
+
+    let mut text = format_table(&columns, &rows);
+
+    let conflicts: Vec<&Value> = fields
+        .iter()
+        .filter(|f| f.get("conflicts").is_some())
+        .collect();
+    if caps && !conflicts.is_empty() {
+        text.push_str(&format!("\nType conflicts ({}):\n", conflicts.len()));
+        for field in conflicts {
+            text.push_str(&format!("  {}\n", field["field"].as_str().unwrap_or("")));
+            if let Some(types) = field["conflicts"].as_object() {
+                for (kind, indices) in types {
+                    let indices: Vec<&str> = indices
+                        .as_array()
+                        .map(|i| i.iter().filter_map(|i| i.as_str()).collect())
+                        .unwrap_or_default();
+                    text.push_str(&format!("    {:<16} {}\n", kind, indices.join(", ")));
+                }
+            }
+        }
+    }
+    if let Some(documents) = output.get("documents") {
+        text.push_str(&format!("\nCoverage over {} documents\n", documents));
+    }
+
+    text
+}
+
 fn format_fields_human(json: &str) -> String {
     let value: serde_json::Value = match serde_json::from_str(json) {
         Ok(v) => v,
@@ -64,11 +307,24 @@ fn format_fields_human(json: &str) -> String {
     output
 }
 
-fn collect_fields(
+/// Flatten mapping properties into (dotted field name, type) pairs
+pub fn collect_fields(
     properties: &serde_json::Value,
     prefix: &str,
     fields: &mut Vec<(String, String)>,
 ) {
+    let mut mappings = Vec::new();
+    collect_field_mappings(properties, prefix, &mut mappings);
+    fields.extend(mappings.into_iter().filter_map(|(name, mapping)| {
+        let field_type = mapping.get("type")?.as_str()?.to_string();
+        Some((name, field_type))
+    }));
+}
+
+/// Flatten mapping properties into (dotted field name, field mapping) pairs.
+/// Each mapping keeps its parameters (type, analyzer, format...) without the
+/// sub-properties and multi-fields, which are listed as fields of their own.
+pub fn collect_field_mappings(properties: &Value, prefix: &str, fields: &mut Vec<(String, Value)>) {
     if let Some(obj) = properties.as_object() {
         for (name, field_data) in obj {
             let full_name = if prefix.is_empty() {
@@ -77,34 +333,24 @@ fn collect_fields(
                 format!("{}.{}", prefix, name)
             };
 
-            if let Some(field_type) = field_data.get("type").and_then(|t| t.as_str()) {
-                fields.push((full_name.clone(), field_type.to_string()));
+            if field_data.get("type").is_some() {
+                let mut mapping = field_data.clone();
+                if let Some(mapping) = mapping.as_object_mut() {
+                    mapping.remove("properties");
+                    mapping.remove("fields");
+                }
+                fields.push((full_name.clone(), mapping));
             }
 
             // Handle nested properties
             if let Some(nested_props) = field_data.get("properties") {
-                collect_fields(nested_props, &full_name, fields);
+                collect_field_mappings(nested_props, &full_name, fields);
             }
 
             // Handle multi-fields (e.g., text with keyword sub-field)
             if let Some(multi_fields) = field_data.get("fields") {
-                if let Some(mf_obj) = multi_fields.as_object() {
-                    for (mf_name, mf_data) in mf_obj {
-                        let mf_full_name = format!("{}.{}", full_name, mf_name);
-                        if let Some(mf_type) = mf_data.get("type").and_then(|t| t.as_str()) {
-                            fields.push((mf_full_name, mf_type.to_string()));
-                        }
-                    }
-                }
+                collect_field_mappings(multi_fields, &full_name, fields);
             }
         }
     }
 }
-
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max - 3])
-    }
-}
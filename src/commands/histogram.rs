@@ -1,36 +1,96 @@
-use crate::client::EsClient;
+use crate::client::{read_body, EsClient};
+use crate::format::truncate;
+use crate::query::parse_optional_query;
+use crate::timerange::{auto_interval, interval_param, TimeArgs};
+use chrono::Local;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Options for the date histogram
+pub struct HistogramOptions<'a> {
+    pub index: &'a str,
+    pub query: Option<&'a str>,
+    pub field: &'a str,
+    pub interval: Option<&'a str>,
+    pub buckets: Option<usize>,
+    pub split_by: Option<&'a str>,
+    pub split_size: usize,
+    pub time: &'a TimeArgs,
+}
+
+/// Bar characters for each series of a split histogram
+const SERIES_CHARS: [char; 8] = ['█', '▓', '▒', '░', '#', '+', '*', '='];
+
+pub async fn run(opts: HistogramOptions<'_>, human: bool) -> Result<(), String> {
+    let range = opts.time.resolve()?;
+    let filter = parse_optional_query(opts.query)?;
+
+    let interval = match opts.buckets {
+        Some(buckets) => {
+            let from = range.from.ok_or_else(|| {
+                "--buckets needs a start time to size the interval (--since or --from)".to_string()
+            })?;
+            let to = range.to.unwrap_or_else(|| Local::now().fixed_offset());
+            auto_interval((to - from).num_seconds(), buckets)
+        }
+        None => opts.interval.unwrap_or("1h").to_string(),
+    };
+    let (interval_key, interval) = interval_param(&interval)?;
+
+    // Empty buckets are kept, and the time range bounds the buckets so that
+    // gaps at both ends show too
+    let mut histogram = json!({
+        "date_histogram": {
+            "field": opts.field,
+            interval_key: interval,
+            "time_zone": opts.time.time_zone(),
+            "min_doc_count": 0
+        }
+    });
+    if !range.is_empty() {
+        let mut bounds = json!({});
+        if let Some(from) = range.from {
+            bounds["min"] = json!(from.timestamp_millis());
+        }
+        if let Some(to) = range
+            .to
+            .or_else(|| range.from.map(|_| Local::now().fixed_offset()))
+        {
+            bounds["max"] = json!(to.timestamp_millis());
+        }
+        histogram["date_histogram"]["extended_bounds"] = bounds;
+    }
+
+    let aggs = match opts.split_by {
+        Some(split_by) => json!({
+            "split": {
+                "terms": { "field": split_by, "size": opts.split_size },
+                "aggs": { "histogram": histogram }
+            }
+        }),
+        None => json!({ "histogram": histogram }),
+    };
 
-pub async fn run(index: &str, field: &str, interval: &str, human: bool) -> Result<(), String> {
     let client = EsClient::new()?;
 
-    let path = format!("/{}/_search", index);
+    let path = format!("/{}/_search", opts.index);
 
-    let query = serde_json::json!({
+    let query = json!({
         "size": 0,
-        "aggs": {
-            "histogram": {
-                "date_histogram": {
-                    "field": field,
-                    "fixed_interval": interval
-                }
-            }
-        }
+        "query": range.apply(filter, opts.field),
+        "aggs": aggs
     });
 
     let response = client.post(&path, &query.to_string()).await?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
 
     if human {
-        println!("{}", format_histogram_human(&body));
+        if opts.split_by.is_some() {
+            println!("{}", format_split_human(&body));
+        } else {
+            println!("{}", format_histogram_human(&body));
+        }
     } else {
         println!("{}", body);
     }
@@ -86,3 +146,104 @@ fn format_histogram_human(json: &str) -> String {
 
     output
 }
+
+/// One column per top term and a stacked bar, one character per series
+fn format_split_human(json: &str) -> String {
+    let value: Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(_) => return json.to_string(),
+    };
+    let series = value
+        .pointer("/aggregations/split/buckets")
+        .and_then(|b| b.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    // Timestamps can differ between series when no time range bounds them:
+    // merge them by bucket key
+    let mut names: Vec<String> = Vec::new();
+    let mut rows: BTreeMap<i64, (String, Vec<u64>)> = BTreeMap::new();
+    for (i, term) in series.iter().enumerate() {
+        names.push(match term.get("key") {
+            Some(Value::String(s)) => s.clone(),
+            Some(key) => key.to_string(),
+            None => "-".to_string(),
+        });
+        let buckets = term
+            .pointer("/histogram/buckets")
+            .and_then(|b| b.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for bucket in buckets {
+            let key = bucket.get("key").and_then(|k| k.as_i64()).unwrap_or(0);
+            let label = bucket
+                .get("key_as_string")
+                .and_then(|k| k.as_str())
+                .unwrap_or("-")
+                .to_string();
+            let count = bucket
+                .get("doc_count")
+                .and_then(|c| c.as_u64())
+                .unwrap_or(0);
+            let row = rows
+                .entry(key)
+                .or_insert_with(|| (label, vec![0; series.len()]));
+            row.1[i] = count;
+        }
+    }
+
+    let width = names
+        .iter()
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(5, 15);
+
+    let mut output = String::new();
+    output.push_str(&format!("{:<30}", "TIMESTAMP"));
+    for name in &names {
+        output.push_str(&format!(
+            " {:>width$}",
+            truncate(name, width),
+            width = width
+        ));
+    }
+    output.push_str(" BAR\n");
+    output.push_str(&"-".repeat(30 + (width + 1) * names.len() + 34));
+    output.push('\n');
+
+    let max_total = rows
+        .values()
+        .map(|(_, counts)| counts.iter().sum::<u64>())
+        .max()
+        .unwrap_or(0);
+
+    let bar_width = 30;
+    for (label, counts) in rows.values() {
+        output.push_str(&format!("{:<30}", label));
+        let mut bar = String::new();
+        for (i, count) in counts.iter().enumerate() {
+            output.push_str(&format!(" {:>width$}", count, width = width));
+            if max_total > 0 {
+                let length = (*count as f64 / max_total as f64 * bar_width as f64) as usize;
+                let c = SERIES_CHARS[i % SERIES_CHARS.len()];
+                bar.extend(std::iter::repeat_n(c, length));
+            }
+        }
+        output.push(' ');
+        output.push_str(&bar);
+        output.push('\n');
+    }
+
+    // Legend
+    output.push('\n');
+    let legend: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| format!("{} {}", SERIES_CHARS[i % SERIES_CHARS.len()], name))
+        .collect();
+    output.push_str(&legend.join("  "));
+    output.push('\n');
+
+    output
+}
@@ -0,0 +1,79 @@
+use crate::commands::saved::{merge_overrides, shell_join};
+use crate::history::{self, Entry};
+use clap::Command;
+use serde_json::json;
+
+pub fn run(search: Option<&str>, size: usize, human: bool) -> Result<(), String> {
+    let entries = history::load()?;
+
+    let matching: Vec<&(usize, Entry)> = entries
+        .iter()
+        .filter(|(_, entry)| search.is_none_or(|s| shell_join(&entry.args).contains(s)))
+        .collect();
+    let shown = &matching[matching.len().saturating_sub(size)..];
+
+    if human {
+        println!("{}", format_history_human(shown));
+    } else {
+        let items: Vec<serde_json::Value> = shown
+            .iter()
+            .map(|(n, entry)| {
+                let mut item = json!(entry);
+                item["id"] = json!(n);
+                item
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(items));
+    }
+    Ok(())
+}
+
+/// Build the argument list to replay history entry `n`, with the overrides
+/// merged in like for saved invocations
+pub fn expand(
+    cli: &Command,
+    n: usize,
+    overrides: &[String],
+    human: bool,
+) -> Result<Vec<String>, String> {
+    let args = history::load()?
+        .into_iter()
+        .find(|(id, _)| *id == n)
+        .map(|(_, entry)| entry.args)
+        .ok_or_else(|| format!("No history entry {}", n))?;
+
+    Ok(merge_overrides(cli, &args, overrides, human))
+}
+
+fn format_history_human(entries: &[&(usize, Entry)]) -> String {
+    let mut output = String::new();
+    output.push_str(&format!(
+        "{:>5}  {:<25} {:>6} {:>8} {:>10}  {}\n",
+        "#", "TIMESTAMP", "STATUS", "TOOK", "HITS", "COMMAND"
+    ));
+    output.push_str(&"-".repeat(100));
+    output.push('\n');
+
+    for (n, entry) in entries {
+        let took = entry
+            .took
+            .map(|t| format!("{}ms", t))
+            .unwrap_or_else(|| "-".to_string());
+        let hits = entry
+            .hits
+            .map(|h| h.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        output.push_str(&format!(
+            "{:>5}  {:<25} {:>6} {:>8} {:>10}  {}\n",
+            n,
+            entry.timestamp,
+            entry.exit_status,
+            took,
+            hits,
+            shell_join(&entry.args)
+        ));
+    }
+
+    output
+}
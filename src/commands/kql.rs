@@ -1,6 +1,10 @@
-use crate::client::EsClient;
-use crate::format::format_output;
-use serde_json::json;
+use crate::client::{read_body, EsClient};
+use crate::commands::async_query;
+use crate::format::{format_groups, format_output, format_profile};
+use crate::group::{self, parse_groups, GroupBy};
+use crate::query::query_string;
+use crate::timerange::TimeArgs;
+use serde_json::{json, Value};
 
 /// Options for KQL queries
 pub struct KqlOptions<'a> {
@@ -9,54 +13,23 @@ pub struct KqlOptions<'a> {
     pub size: usize,
     pub sort: Option<&'a str>,
     pub fields: Option<&'a str>,
-    pub since: Option<&'a str>,
-    pub from: Option<&'a str>,
-    pub to: Option<&'a str>,
+    pub group_by: Option<&'a str>,
+    pub per_group: usize,
+    pub time: &'a TimeArgs,
     pub timestamp_field: &'a str,
+    pub profile: bool,
+    pub run_async: bool,
 }
 
 pub async fn run(opts: KqlOptions<'_>, human: bool) -> Result<(), String> {
+    let range = opts.time.resolve()?;
     let client = EsClient::new()?;
     let path = format!("/{}/_search", opts.index);
 
-    // Build the query using query_string which respects field mappings better than
-    // simple_query_string (e.g. keyword fields, wildcards, NOT operator).
-    // lenient=true prevents errors on type mismatches, analyze_wildcard enables
-    // wildcard expansion on analyzed fields.
-    let query_clause = json!({
-        "query_string": {
-            "query": opts.query,
-            "default_operator": "AND",
-            "lenient": true,
-            "analyze_wildcard": true
-        }
-    });
+    let query_clause = query_string(opts.query);
 
     // Add time range filter if any time options are specified
-    let has_time_filter = opts.since.is_some() || opts.from.is_some() || opts.to.is_some();
-
-    let final_query = if has_time_filter {
-        let mut range = json!({});
-
-        if let Some(since) = opts.since {
-            range[opts.timestamp_field]["gte"] = json!(format!("now-{}", since));
-        }
-        if let Some(from) = opts.from {
-            range[opts.timestamp_field]["gte"] = json!(from);
-        }
-        if let Some(to) = opts.to {
-            range[opts.timestamp_field]["lte"] = json!(to);
-        }
-
-        json!({
-            "bool": {
-                "must": [query_clause],
-                "filter": [{ "range": range }]
-            }
-        })
-    } else {
-        query_clause
-    };
+    let final_query = range.apply(query_clause, opts.timestamp_field);
 
     // Build the request body
     let mut body = json!({
@@ -82,17 +55,37 @@ pub async fn run(opts: KqlOptions<'_>, human: bool) -> Result<(), String> {
         body["_source"] = json!(field_list);
     }
 
-    let response = client.post(&path, &body.to_string()).await?;
+    if opts.profile {
+        body["profile"] = json!(true);
+    }
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
+    if let Some(field) = opts.group_by {
+        let group = GroupBy {
+            field,
+            per_group: opts.per_group,
+        };
+        let body = group::search(&client, &path, &body, &group).await?;
+        if human {
+            let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+            println!("{}", format_groups(field, &parse_groups(&value, field)));
+        } else {
+            println!("{}", body);
+        }
+        if opts.profile && human {
+            println!("{}", format_profile(&body));
+        }
+        return Ok(());
     }
 
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = if opts.run_async {
+        async_query::search(&client, opts.index, &body).await?
+    } else {
+        let response = client.post(&path, &body.to_string()).await?;
+        read_body(response).await?
+    };
     println!("{}", format_output(&body, human));
+    if opts.profile && human {
+        println!("{}", format_profile(&body));
+    }
     Ok(())
 }
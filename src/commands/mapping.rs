@@ -0,0 +1,234 @@
+use crate::client::{read_body, EsClient};
+use crate::commands::fields::collect_field_mappings;
+use crate::format::{format_table, format_value};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// Exit status when the mappings differ, distinct from the status of errors (1)
+pub const DIFFERENCES_STATUS: i32 = 2;
+
+/// Compare the mapping of `old` (an index, or the baseline file) with `new`.
+/// Returns whether they differ.
+pub async fn diff(
+    old: &str,
+    new: Option<&str>,
+    baseline: Option<&str>,
+    human: bool,
+) -> Result<bool, String> {
+    let client = EsClient::new()?;
+
+    let (old_label, old_fields, new_label, new_fields) = match (baseline, new) {
+        (Some(file), _) => {
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| format!("Cannot read {}: {}", file, e))?;
+            let value: Value = serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid JSON in {}: {}", file, e))?;
+            let properties = properties_of(&value).ok_or_else(|| {
+                format!(
+                    "No mapping found in {}: expected the GET _mapping output of a single \
+                     index, an index template or a mappings object",
+                    file
+                )
+            })?;
+            let baseline = flatten(properties);
+            (file, baseline, old, fetch(&client, old).await?)
+        }
+        (None, Some(new)) => (
+            old,
+            fetch(&client, old).await?,
+            new,
+            fetch(&client, new).await?,
+        ),
+        (None, None) => return Err("Give a second index or --baseline <file>".to_string()),
+    };
+
+    let output = compare(&old_fields, &new_fields);
+    if human {
+        println!("{}", format_diff_human(&output, old_label, new_label));
+    } else {
+        println!("{}", output);
+    }
+
+    let count = |key: &str| output[key].as_array().map(Vec::len).unwrap_or(0);
+    let (added, removed, changed) = (count("added"), count("removed"), count("changed"));
+    let differ = added + removed + changed > 0;
+    if differ {
+        eprintln!(
+            "Mappings differ: {} added, {} removed, {} changed",
+            added, removed, changed
+        );
+    }
+    Ok(differ)
+}
+
+/// Flattened mapping of one index
+async fn fetch(client: &EsClient, index: &str) -> Result<BTreeMap<String, Value>, String> {
+    let response = client.get(&format!("/{}/_mapping", index)).await?;
+    let value: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+
+    let indices: Vec<&String> = value
+        .as_object()
+        .map(|o| o.keys().collect())
+        .unwrap_or_default();
+    if indices.len() > 1 {
+        let names: Vec<&str> = indices.iter().map(|i| i.as_str()).collect();
+        return Err(format!(
+            "{} matches {} indices ({}), compare single indices",
+            index,
+            indices.len(),
+            names.join(", ")
+        ));
+    }
+    Ok(flatten(properties_of(&value).unwrap_or(&NO_PROPERTIES)))
+}
+
+static NO_PROPERTIES: Value = Value::Null;
+
+/// Properties of a mapping given as a GET _mapping response, an index template
+/// (`template.mappings`), a `mappings` object or bare `properties`
+fn properties_of(value: &Value) -> Option<&Value> {
+    if let Some(properties) = value.get("properties") {
+        return Some(properties);
+    }
+    // A mapping without fields yet
+    if let Some(mappings) = value.get("mappings") {
+        return properties_of(mappings).or(Some(&NO_PROPERTIES));
+    }
+    if let Some(template) = value.get("template") {
+        return properties_of(template);
+    }
+    // GET _mapping response: { "<index>": { "mappings": ... } }
+    match value.as_object() {
+        Some(obj) if obj.len() == 1 => properties_of(obj.values().next()?),
+        _ => None,
+    }
+}
+
+fn flatten(properties: &Value) -> BTreeMap<String, Value> {
+    let mut fields = Vec::new();
+    collect_field_mappings(properties, "", &mut fields);
+    fields.into_iter().collect()
+}
+
+/// Fields only in the new mapping, only in the old one, and the parameters
+/// (type, analyzer, format...) that differ between both
+fn compare(old: &BTreeMap<String, Value>, new: &BTreeMap<String, Value>) -> Value {
+    let added: Vec<Value> = new
+        .iter()
+        .filter(|(field, _)| !old.contains_key(*field))
+        .map(|(field, mapping)| json!({ "field": field, "mapping": mapping }))
+        .collect();
+    let removed: Vec<Value> = old
+        .iter()
+        .filter(|(field, _)| !new.contains_key(*field))
+        .map(|(field, mapping)| json!({ "field": field, "mapping": mapping }))
+        .collect();
+
+    let mut changed = Vec::new();
+    for (field, old_mapping) in old {
+        let Some(new_mapping) = new.get(field) else {
+            continue;
+        };
+        let empty = Map::new();
+        let old_params = old_mapping.as_object().unwrap_or(&empty);
+        let new_params = new_mapping.as_object().unwrap_or(&empty);
+
+        let mut params: Vec<&String> = old_params.keys().chain(new_params.keys()).collect();
+        params.sort();
+        params.dedup();
+
+        let mut changes = Map::new();
+        for param in params {
+            let (from, to) = (old_params.get(param), new_params.get(param));
+            if from != to {
+                changes.insert(param.clone(), json!({ "from": from, "to": to }));
+            }
+        }
+        if !changes.is_empty() {
+            changed.push(json!({ "field": field, "changes": changes }));
+        }
+    }
+
+    json!({ "added": added, "removed": removed, "changed": changed })
+}
+
+fn format_diff_human(output: &Value, old: &str, new: &str) -> String {
+    let mut text = format!("--- {}\n+++ {}\n\n", old, new);
+    let items = |key: &str| output[key].as_array().cloned().unwrap_or_default();
+    let (added, removed, changed) = (items("added"), items("removed"), items("changed"));
+
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        text.push_str("No differences\n");
+        return text;
+    }
+
+    let columns = |names: &[&str]| -> Vec<String> { names.iter().map(|c| c.to_string()).collect() };
+    for (title, items, sign) in [("Added", &added, "+"), ("Removed", &removed, "-")] {
+        if items.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("{} ({}):\n", title, items.len()));
+        let rows: Vec<Vec<Value>> = items
+            .iter()
+            .map(|item| {
+                vec![
+                    json!(format!("{} {}", sign, format_value(&item["field"]))),
+                    item["mapping"]["type"].clone(),
+                    json!(other_params(&item["mapping"])),
+                ]
+            })
+            .collect();
+        text.push_str(&format_table(
+            &columns(&["field", "type", "parameters"]),
+            &rows,
+        ));
+        text.push('\n');
+    }
+
+    if !changed.is_empty() {
+        text.push_str(&format!("Changed ({}):\n", changed.len()));
+        let mut rows: Vec<Vec<Value>> = Vec::new();
+        for item in &changed {
+            if let Some(changes) = item["changes"].as_object() {
+                for (param, change) in changes {
+                    rows.push(vec![
+                        json!(format!("~ {}", format_value(&item["field"]))),
+                        json!(param),
+                        json!(param_value(&change["from"])),
+                        json!(param_value(&change["to"])),
+                    ]);
+                }
+            }
+        }
+        text.push_str(&format_table(
+            &columns(&["field", "parameter", "from", "to"]),
+            &rows,
+        ));
+    }
+
+    text
+}
+
+/// Mapping parameters besides the type, as `key=value` pairs
+fn other_params(mapping: &Value) -> String {
+    mapping
+        .as_object()
+        .map(|params| {
+            params
+                .iter()
+                .filter(|(key, _)| key.as_str() != "type")
+                .map(|(key, value)| format!("{}={}", key, param_value(value)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+fn param_value(value: &Value) -> String {
+    match value {
+        Value::Null => "(none)".to_string(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
@@ -1,13 +1,29 @@
+pub mod agg;
 pub mod aliases;
+pub mod async_query;
+pub mod compare;
 pub mod count;
 pub mod datastreams;
+pub mod describe;
+pub mod doc;
+pub mod eql;
 pub mod esql;
+pub mod explain;
 pub mod fields;
 pub mod get;
 pub mod histogram;
+pub mod history;
 pub mod kql;
 pub mod list;
+pub mod mapping;
+pub mod msearch;
+pub mod repl;
+pub mod sample;
+pub mod saved;
 pub mod search;
+pub mod sql;
 pub mod stats;
 pub mod tail;
+pub mod template;
+pub mod validate;
 pub mod values;
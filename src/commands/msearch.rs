@@ -0,0 +1,202 @@
+use crate::client::{read_body, EsClient};
+use crate::format::format_output;
+use crate::query::parse_query;
+use serde_json::{json, Value};
+use std::io::Read;
+
+/// One search of the batch: a label for display, the msearch header and the body
+struct SearchItem {
+    label: String,
+    header: Value,
+    body: Value,
+}
+
+pub async fn run(file: &str, batch_size: usize, human: bool) -> Result<(), String> {
+    let content = if file == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Cannot read stdin: {}", e))?;
+        content
+    } else {
+        std::fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?
+    };
+
+    let items = if file.ends_with(".yaml") || file.ends_with(".yml") {
+        parse_yaml(&content)?
+    } else {
+        parse_ndjson(&content)?
+    };
+    if items.is_empty() {
+        return Err(format!("No searches found in {}", file));
+    }
+
+    let client = EsClient::new()?;
+    let mut results: Vec<Value> = Vec::new();
+    let mut failed = 0;
+
+    for batch in items.chunks(batch_size.max(1)) {
+        let mut request = String::new();
+        for item in batch {
+            request.push_str(&item.header.to_string());
+            request.push('\n');
+            request.push_str(&item.body.to_string());
+            request.push('\n');
+        }
+
+        // A failed batch is reported on each of its searches, the next batches still run
+        let responses = match send_batch(&client, &request).await {
+            Ok(responses) => responses,
+            Err(e) => vec![json!({ "error": { "reason": e } }); batch.len()],
+        };
+
+        // Searches without a response in a short answer are errors too
+        let missing = json!({ "error": { "reason": "No response from _msearch" } });
+        let responses = responses
+            .into_iter()
+            .chain(std::iter::repeat(missing))
+            .take(batch.len());
+
+        for (item, response) in batch.iter().zip(responses) {
+            if response.get("error").is_some() {
+                failed += 1;
+            }
+            if human {
+                println!("{}", format_item_human(item, &response));
+            }
+            results.push(json!({
+                "label": item.label,
+                "index": item.header.get("index"),
+                "response": response
+            }));
+        }
+    }
+
+    if !human {
+        println!("{}", Value::Array(results));
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} searches failed", failed, items.len()));
+    }
+    Ok(())
+}
+
+async fn send_batch(client: &EsClient, request: &str) -> Result<Vec<Value>, String> {
+    let response = client.post_ndjson("/_msearch", request).await?;
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    value
+        .get("responses")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .ok_or_else(|| "Invalid _msearch response: missing responses".to_string())
+}
+
+/// Standard _msearch NDJSON: header and body lines alternate. A "label" key in
+/// the header is used for display and not sent to Elasticsearch.
+fn parse_ndjson(content: &str) -> Result<Vec<SearchItem>, String> {
+    let lines: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect();
+    if !lines.len().is_multiple_of(2) {
+        return Err("Expected header and body lines in pairs".to_string());
+    }
+
+    lines
+        .chunks(2)
+        .enumerate()
+        .map(|(n, pair)| {
+            let (header_line, header) = pair[0];
+            let (body_line, body) = pair[1];
+            let mut header: Value = serde_json::from_str(header)
+                .map_err(|e| format!("Line {}: invalid JSON header: {}", header_line + 1, e))?;
+            let body: Value = serde_json::from_str(body)
+                .map_err(|e| format!("Line {}: invalid JSON body: {}", body_line + 1, e))?;
+
+            let label = header
+                .as_object_mut()
+                .and_then(|h| h.remove("label"))
+                .and_then(|l| l.as_str().map(String::from))
+                .unwrap_or_else(|| default_label(n, &header));
+            Ok(SearchItem {
+                label,
+                header,
+                body,
+            })
+        })
+        .collect()
+}
+
+/// Simpler YAML list, e.g.:
+///
+/// - label: api errors
+///   index: logs-*
+///   query: "status:error AND service:api"   # KQL/Lucene, JSON DSL or a YAML mapping
+///   size: 5
+fn parse_yaml(content: &str) -> Result<Vec<SearchItem>, String> {
+    let entries: Vec<Value> =
+        serde_yaml::from_str(content).map_err(|e| format!("Invalid YAML: {}", e))?;
+
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(n, entry)| {
+            let index = entry
+                .get("index")
+                .and_then(|i| i.as_str())
+                .ok_or_else(|| format!("Entry {}: missing index", n + 1))?;
+            let query = match entry.get("query") {
+                None => json!({ "match_all": {} }),
+                Some(Value::String(q)) => parse_query(q)?,
+                Some(q) => q.get("query").unwrap_or(q).clone(),
+            };
+            let size = entry.get("size").and_then(|s| s.as_u64()).unwrap_or(10);
+
+            let header = json!({ "index": index });
+            let label = entry
+                .get("label")
+                .and_then(|l| l.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| default_label(n, &header));
+            Ok(SearchItem {
+                label,
+                header,
+                body: json!({ "query": query, "size": size }),
+            })
+        })
+        .collect()
+}
+
+fn default_label(n: usize, header: &Value) -> String {
+    match header.get("index") {
+        Some(Value::String(index)) => format!("#{} {}", n + 1, index),
+        Some(index) => format!("#{} {}", n + 1, index),
+        None => format!("#{}", n + 1),
+    }
+}
+
+fn format_item_human(item: &SearchItem, response: &Value) -> String {
+    let mut output = format!("=== {} ===\n", item.label);
+
+    match response.get("error") {
+        Some(error) => {
+            let kind = error.get("type").and_then(|t| t.as_str());
+            let reason = error
+                .get("reason")
+                .and_then(|r| r.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| error.to_string());
+            match kind {
+                Some(kind) => output.push_str(&format!("Error: {}: {}\n", kind, reason)),
+                None => output.push_str(&format!("Error: {}\n", reason)),
+            }
+        }
+        None => output.push_str(&format_output(&response.to_string(), true)),
+    }
+
+    output
+}
@@ -0,0 +1,518 @@
+use crate::client::{read_body, EsClient};
+use crate::commands::fields::collect_fields;
+use crate::config::{config_dir, Config};
+use crate::format::{format_output, format_profile};
+use crate::query::query_string;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+use serde_json::{json, Value};
+
+/// ES|QL source and processing commands, keywords and functions, for completion
+const ESQL_WORDS: &[&str] = &[
+    // Commands
+    "FROM",
+    "ROW",
+    "SHOW",
+    "WHERE",
+    "EVAL",
+    "STATS",
+    "KEEP",
+    "DROP",
+    "RENAME",
+    "SORT",
+    "LIMIT",
+    "DISSECT",
+    "GROK",
+    "ENRICH",
+    "MV_EXPAND",
+    "LOOKUP",
+    "JOIN",
+    // Keywords
+    "BY",
+    "AS",
+    "ON",
+    "WITH",
+    "METADATA",
+    "ASC",
+    "DESC",
+    "NULLS",
+    "FIRST",
+    "LAST",
+    "AND",
+    "OR",
+    "NOT",
+    "IS",
+    "NULL",
+    "LIKE",
+    "RLIKE",
+    "IN",
+    "TRUE",
+    "FALSE",
+    // Aggregation functions
+    "AVG",
+    "COUNT",
+    "COUNT_DISTINCT",
+    "MAX",
+    "MEDIAN",
+    "MEDIAN_ABSOLUTE_DEVIATION",
+    "MIN",
+    "PERCENTILE",
+    "SUM",
+    "TOP",
+    "VALUES",
+    "WEIGHTED_AVG",
+    // Grouping, math, string, date, conversion and multivalue functions
+    "BUCKET",
+    "ABS",
+    "CEIL",
+    "FLOOR",
+    "ROUND",
+    "POW",
+    "SQRT",
+    "LOG",
+    "LOG10",
+    "CONCAT",
+    "LENGTH",
+    "LEFT",
+    "RIGHT",
+    "SUBSTRING",
+    "TRIM",
+    "LTRIM",
+    "RTRIM",
+    "TO_LOWER",
+    "TO_UPPER",
+    "REPLACE",
+    "SPLIT",
+    "STARTS_WITH",
+    "ENDS_WITH",
+    "DATE_DIFF",
+    "DATE_EXTRACT",
+    "DATE_FORMAT",
+    "DATE_PARSE",
+    "DATE_TRUNC",
+    "NOW",
+    "TO_STRING",
+    "TO_INTEGER",
+    "TO_LONG",
+    "TO_DOUBLE",
+    "TO_DATETIME",
+    "TO_BOOLEAN",
+    "TO_IP",
+    "CASE",
+    "COALESCE",
+    "GREATEST",
+    "LEAST",
+    "CIDR_MATCH",
+    "MV_AVG",
+    "MV_CONCAT",
+    "MV_COUNT",
+    "MV_DEDUPE",
+    "MV_MAX",
+    "MV_MIN",
+    "MV_SUM",
+];
+
+const META_COMMANDS: &[&str] = &[
+    ".help", ".mode", ".index", ".kql", ".timing", ".profile", ".url", ".quit", ".exit",
+];
+
+const HELP: &str = "\
+Enter ES|QL queries, ended by ';' or an empty line. Query DSL (starting with '{')
+runs as a search on the current index. Tab completes ES|QL words, index names and
+the fields of the current index.
+
+  .mode table|json     Output format (default: table)
+  .index <name>        Index for .kql and Query DSL, and for field completion
+  .kql <query>         Run a KQL/Lucene query on the current index
+  .timing on|off       Profile queries and show a timing tree
+  .profile [<name>]    List connection profiles, or switch to one
+  .url [<url>]         Show or change the cluster URL (same credentials)
+  .quit                Exit (or Ctrl-D)";
+
+/// Completion candidates and the multi-line rule for the line editor
+struct ReplHelper {
+    indices: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(|c: char| !(c.is_alphanumeric() || "_.-*@".contains(c)))
+            .map(|i| i + before[i..].chars().next().map_or(1, |c| c.len_utf8()))
+            .unwrap_or(0);
+        let word = &before[start..];
+
+        // Meta commands at the start of the line
+        if start == 0 && word.starts_with('.') {
+            let candidates = META_COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(word))
+                .map(|c| c.to_string())
+                .collect();
+            return Ok((start, candidates));
+        }
+
+        // Index names in a FROM clause or after .index, words and fields elsewhere
+        let segment = before[..start]
+            .rsplit('|')
+            .next()
+            .unwrap_or("")
+            .trim_start();
+        let in_from = start > 0
+            && ((segment.to_lowercase().starts_with("from ")
+                && !segment.to_lowercase().contains(" metadata "))
+                || segment.starts_with(".index "));
+        if in_from {
+            let candidates = self
+                .indices
+                .iter()
+                .filter(|i| i.starts_with(word))
+                .cloned()
+                .collect();
+            return Ok((start, candidates));
+        }
+
+        let upper = word.to_uppercase();
+        let mut candidates: Vec<String> = self
+            .fields
+            .iter()
+            .filter(|f| f.starts_with(word))
+            .cloned()
+            .collect();
+        if !word.is_empty() {
+            candidates.extend(
+                ESQL_WORDS
+                    .iter()
+                    .filter(|w| w.starts_with(&upper))
+                    .map(|w| w.to_string()),
+            );
+        }
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    /// Queries continue on the next line until they end with ';' or an empty line
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        let trimmed = input.trim();
+        if trimmed.is_empty()
+            || trimmed.starts_with('.')
+            || trimmed.ends_with(';')
+            || input.ends_with('\n')
+        {
+            Ok(ValidationResult::Valid(None))
+        } else {
+            Ok(ValidationResult::Incomplete)
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+/// Session settings changed by meta commands
+struct Session {
+    client: EsClient,
+    /// Connection profile in use, if any
+    connection: Option<String>,
+    table: bool,
+    timing: bool,
+    index: Option<String>,
+}
+
+/// Completion lists to reload after an input
+enum Refresh {
+    /// The fields of a newly targeted index
+    Fields(String),
+    /// Index names and fields, after switching cluster
+    Cluster,
+}
+
+pub async fn run() -> Result<(), String> {
+    let mut session = Session {
+        client: EsClient::new()?,
+        connection: None,
+        table: true,
+        timing: false,
+        index: None,
+    };
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> =
+        Editor::new().map_err(|e| e.to_string())?;
+    editor.set_helper(Some(ReplHelper {
+        indices: fetch_indices(&session.client).await,
+        fields: Vec::new(),
+    }));
+
+    let dir = config_dir()?;
+    let history = dir.join("repl_history");
+    let _ = editor.load_history(&history);
+
+    println!("es-cli REPL: ES|QL queries end with ';' or an empty line. Type .help for commands.");
+
+    let mut failure = None;
+    loop {
+        let prompt = match &session.connection {
+            Some(name) => format!("esql ({})> ", name),
+            None => "esql> ".to_string(),
+        };
+        let input = match editor.readline(&prompt) {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                failure = Some(e.to_string());
+                break;
+            }
+        };
+        let input = input.trim().trim_end_matches(';').trim();
+        if input.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input);
+
+        if matches!(input, ".quit" | ".exit") {
+            break;
+        }
+
+        match execute(&mut session, input).await {
+            Ok(Some(Refresh::Fields(index))) => {
+                let fields = fetch_fields(&session.client, &index).await;
+                if let Some(helper) = editor.helper_mut() {
+                    helper.fields = fields;
+                }
+            }
+            Ok(Some(Refresh::Cluster)) => {
+                let indices = fetch_indices(&session.client).await;
+                let fields = match &session.index {
+                    Some(index) => fetch_fields(&session.client, index).await,
+                    None => Vec::new(),
+                };
+                if let Some(helper) = editor.helper_mut() {
+                    helper.indices = indices;
+                    helper.fields = fields;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    // Keep the history of a session that ends on a terminal error too
+    let _ = std::fs::create_dir_all(&dir);
+    let saved = editor
+        .save_history(&history)
+        .map_err(|e| format!("Cannot write {}: {}", history.display(), e));
+    match failure {
+        Some(e) => Err(e),
+        None => saved,
+    }
+}
+
+/// Run one input. Returns the completion lists it makes stale, if any.
+async fn execute(session: &mut Session, input: &str) -> Result<Option<Refresh>, String> {
+    if let Some(query) = input.strip_prefix(".kql ") {
+        let index = session
+            .index
+            .clone()
+            .ok_or("No index for .kql: set one with .index <name>")?;
+        let mut body = json!({ "query": query_string(query.trim()), "size": 10 });
+        if session.timing {
+            body["profile"] = json!(true);
+        }
+        search(session, &index, &body).await?;
+        return Ok(None);
+    }
+
+    if input.starts_with('.') {
+        return meta_command(session, input);
+    }
+
+    if input.starts_with('{') {
+        let index = session
+            .index
+            .clone()
+            .ok_or("No index for Query DSL: set one with .index <name>")?;
+        let mut body: Value =
+            serde_json::from_str(input).map_err(|e| format!("Invalid JSON query: {e}"))?;
+        if session.timing {
+            body["profile"] = json!(true);
+        }
+        search(session, &index, &body).await?;
+        return Ok(None);
+    }
+
+    let mut body = json!({ "query": input });
+    if session.timing {
+        body["profile"] = json!(true);
+    }
+    let response = session.client.post("/_query", &body.to_string()).await?;
+    let body = read_body(response).await?;
+    print_response(session, &body);
+
+    // Complete the fields of the queried index from now on
+    let index = input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .windows(2)
+        .find(|w| w[0].eq_ignore_ascii_case("from"))
+        .map(|w| w[1].trim_end_matches(['|', ',']).to_string());
+    if index.is_some() && index != session.index {
+        session.index = index.clone();
+        return Ok(index.map(Refresh::Fields));
+    }
+    Ok(None)
+}
+
+fn meta_command(session: &mut Session, input: &str) -> Result<Option<Refresh>, String> {
+    let (command, arg) = input
+        .split_once(char::is_whitespace)
+        .map(|(c, a)| (c, a.trim()))
+        .unwrap_or((input, ""));
+
+    match (command, arg) {
+        (".help", _) => println!("{}", HELP),
+        (".mode", "table") => session.table = true,
+        (".mode", "json") => session.table = false,
+        (".mode", "") => println!("{}", if session.table { "table" } else { "json" }),
+        (".mode", _) => return Err("Usage: .mode table|json".to_string()),
+        (".index", "") => println!("{}", session.index.as_deref().unwrap_or("(none)")),
+        (".index", index) => {
+            session.index = Some(index.to_string());
+            return Ok(Some(Refresh::Fields(index.to_string())));
+        }
+        (".timing", "on") => session.timing = true,
+        (".timing", "off") => session.timing = false,
+        (".timing", "") => println!("{}", if session.timing { "on" } else { "off" }),
+        (".timing", _) => return Err("Usage: .timing on|off".to_string()),
+        (".profile", "") => {
+            let config = Config::load()?;
+            if config.profiles.is_empty() {
+                println!("No connection profiles (add them under \"profiles\" in config.json)");
+            }
+            for (name, profile) in &config.profiles {
+                let current = session.connection.as_deref() == Some(name.as_str());
+                println!(
+                    "{} {}  {}",
+                    if current { "*" } else { " " },
+                    name,
+                    profile.url
+                );
+            }
+        }
+        (".profile", name) => {
+            let config = Config::load()?;
+            let profile = config
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("No connection profile named '{}'", name))?;
+            session.client = EsClient::from_profile(profile)?;
+            session.connection = Some(name.to_string());
+            return Ok(Some(Refresh::Cluster));
+        }
+        (".url", "") => println!("{}", session.client.base_url()),
+        (".url", url) => {
+            session.client = session.client.with_url(url);
+            session.connection = None;
+            return Ok(Some(Refresh::Cluster));
+        }
+        (".kql", _) => return Err("Usage: .kql <query>".to_string()),
+        _ => return Err(format!("Unknown command {} (see .help)", command)),
+    }
+    Ok(None)
+}
+
+async fn search(session: &Session, index: &str, body: &Value) -> Result<(), String> {
+    let path = format!("/{}/_search", index);
+    let response = session.client.post(&path, &body.to_string()).await?;
+    let body = read_body(response).await?;
+    print_response(session, &body);
+    Ok(())
+}
+
+fn print_response(session: &Session, body: &str) {
+    if session.table {
+        println!("{}", format_output(body, true));
+        if session.timing {
+            println!("{}", format_profile(body));
+        }
+    } else {
+        let pretty = serde_json::from_str::<Value>(body)
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .unwrap_or_else(|_| body.to_string());
+        println!("{}", pretty);
+    }
+}
+
+/// Index, alias and data stream names, for completion. Empty when they cannot be listed.
+async fn fetch_indices(client: &EsClient) -> Vec<String> {
+    let mut names = Vec::new();
+    for (path, key) in [
+        ("/_cat/indices?format=json&h=index", "index"),
+        ("/_cat/aliases?format=json&h=alias", "alias"),
+    ] {
+        let Ok(response) = client.get(path).await else {
+            continue;
+        };
+        let Ok(body) = read_body(response).await else {
+            continue;
+        };
+        if let Ok(Value::Array(rows)) = serde_json::from_str(&body) {
+            names.extend(
+                rows.iter()
+                    .filter_map(|r| r.get(key).and_then(|n| n.as_str()))
+                    .map(String::from),
+            );
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Field names of an index, for completion. Empty when the mapping cannot be read.
+async fn fetch_fields(client: &EsClient, index: &str) -> Vec<String> {
+    let path = format!("/{}/_mapping", index);
+    let Ok(response) = client.get(&path).await else {
+        return Vec::new();
+    };
+    let Ok(body) = read_body(response).await else {
+        return Vec::new();
+    };
+    let Ok(value) = serde_json::from_str::<Value>(&body) else {
+        return Vec::new();
+    };
+
+    let mut fields = Vec::new();
+    if let Some(indices) = value.as_object() {
+        for data in indices.values() {
+            if let Some(properties) = data.pointer("/mappings/properties") {
+                collect_fields(properties, "", &mut fields);
+            }
+        }
+    }
+    let mut names: Vec<String> = fields.into_iter().map(|(name, _)| name).collect();
+    names.sort();
+    names.dedup();
+    names
+}
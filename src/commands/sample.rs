@@ -0,0 +1,103 @@
+use crate::client::{read_body, EsClient};
+use crate::format::format_output;
+use crate::query::{parse_optional_query, sampler_probability};
+use serde_json::{json, Value};
+
+/// Options for random document samples
+pub struct SampleOptions<'a> {
+    pub index: &'a str,
+    pub query: Option<&'a str>,
+    pub size: usize,
+    pub seed: Option<i32>,
+    pub sampler: bool,
+}
+
+pub async fn run(opts: SampleOptions<'_>, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let query = parse_optional_query(opts.query)?;
+    let path = format!("/{}/_search", opts.index);
+
+    if opts.sampler {
+        // Aim for ~10 times the documents wanted, so that the sample is not just
+        // the first matches of one shard. Small result sets are cheap enough to
+        // score randomly instead.
+        let total = count(&client, opts.index, &query).await?;
+        if let Some(probability) = sampler_probability(opts.size as u64 * 10, total) {
+            let body = sampler_body(&opts, query, probability);
+            let response = client.post(&path, &body.to_string()).await?;
+            let body = read_body(response).await?;
+            let output = sampler_hits(&body)?;
+            println!("{}", format_output(&output, human));
+            return Ok(());
+        }
+    }
+
+    let body = random_score_body(&opts, query);
+    let response = client.post(&path, &body.to_string()).await?;
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+/// Matching documents in a random order
+fn random_score_body(opts: &SampleOptions, query: Value) -> Value {
+    json!({
+        "size": opts.size,
+        "query": random_score_query(query, opts.seed)
+    })
+}
+
+/// Documents from a random_sampler aggregation, which skips over the index
+/// instead of scoring every match. The sampled documents are scored randomly
+/// too: with equal scores, top_hits would return the first ones of the first shard.
+fn sampler_body(opts: &SampleOptions, query: Value, probability: f64) -> Value {
+    let mut sampler = json!({ "probability": probability });
+    if let Some(seed) = opts.seed {
+        sampler["seed"] = json!(seed);
+    }
+    json!({
+        "size": 0,
+        "query": random_score_query(query, opts.seed),
+        "aggs": {
+            "sample": {
+                "random_sampler": sampler,
+                "aggs": { "docs": { "top_hits": { "size": opts.size } } }
+            }
+        }
+    })
+}
+
+/// Score the query's matches randomly. With a seed, the order is stable as long
+/// as the documents don't change.
+fn random_score_query(query: Value, seed: Option<i32>) -> Value {
+    let random_score = match seed {
+        Some(seed) => json!({ "seed": seed, "field": "_seq_no" }),
+        None => json!({}),
+    };
+    json!({
+        "function_score": {
+            "query": query,
+            "random_score": random_score,
+            "boost_mode": "replace"
+        }
+    })
+}
+
+/// Reshape the sampler response like a search response
+fn sampler_hits(body: &str) -> Result<String, String> {
+    let value: Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    let hits = value
+        .pointer("/aggregations/sample/docs/hits")
+        .cloned()
+        .ok_or_else(|| "Invalid response: missing sampled documents".to_string())?;
+    Ok(json!({ "took": value.get("took"), "hits": hits }).to_string())
+}
+
+async fn count(client: &EsClient, index: &str, query: &Value) -> Result<u64, String> {
+    let path = format!("/{}/_count", index);
+    let body = json!({ "query": query });
+    let response = client.post(&path, &body.to_string()).await?;
+    let value: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+    Ok(value.get("count").and_then(|c| c.as_u64()).unwrap_or(0))
+}
@@ -0,0 +1,153 @@
+use crate::config::Config;
+use clap::{Arg, Command};
+use std::collections::HashSet;
+
+pub fn add(name: &str, args: &[String]) -> Result<(), String> {
+    let mut config = Config::load()?;
+    config.saved.insert(name.to_string(), args.to_vec());
+    config.save()
+}
+
+pub fn list(human: bool) -> Result<(), String> {
+    let config = Config::load()?;
+
+    if human {
+        println!("{}", format_saved_human(&config));
+    } else {
+        let body = serde_json::to_string(&config.saved).map_err(|e| e.to_string())?;
+        println!("{}", body);
+    }
+    Ok(())
+}
+
+pub fn remove(name: &str) -> Result<(), String> {
+    let mut config = Config::load()?;
+    if config.saved.remove(name).is_none() {
+        return Err(format!("No saved query named '{}'", name));
+    }
+    config.save()
+}
+
+/// Build the argument list for a saved invocation, with the command line overrides
+/// merged in (see `merge_overrides`)
+pub fn expand(
+    cli: &Command,
+    name: &str,
+    overrides: &[String],
+    human: bool,
+) -> Result<Vec<String>, String> {
+    let config = Config::load()?;
+    let args = config
+        .saved
+        .get(name)
+        .ok_or_else(|| format!("No saved query named '{}'", name))?;
+
+    Ok(merge_overrides(cli, args, overrides, human))
+}
+
+/// Time options that together make the time range
+const TIME_OPTIONS: [&str; 3] = ["since", "from", "to"];
+
+/// Merge override arguments into saved ones, so that each option appears once.
+/// An option given in the overrides replaces its saved occurrences, whatever the
+/// spelling (-n or --size), and any time option replaces the whole saved time
+/// range: `--from yesterday` drops a saved `--since 1h`.
+pub fn merge_overrides(
+    cli: &Command,
+    args: &[String],
+    overrides: &[String],
+    human: bool,
+) -> Vec<String> {
+    let command = args.first().and_then(|name| cli.find_subcommand(name));
+    let option = |token: &str| option_of(cli, command, token);
+
+    let replaced: HashSet<String> = overrides
+        .iter()
+        .filter_map(|token| option(token))
+        .map(|(id, _)| id)
+        .collect();
+    let replaces_time = replaced
+        .iter()
+        .any(|id| TIME_OPTIONS.contains(&id.as_str()));
+
+    let mut merged = Vec::new();
+    let mut tokens = args.iter();
+    while let Some(token) = tokens.next() {
+        let Some((id, takes_value)) = option(token) else {
+            merged.push(token.clone());
+            continue;
+        };
+        // The value of `--opt value`, not of `--opt=value`
+        let value = if takes_value && !token.contains('=') {
+            tokens.next()
+        } else {
+            None
+        };
+        if replaced.contains(&id) || (replaces_time && TIME_OPTIONS.contains(&id.as_str())) {
+            continue;
+        }
+        merged.push(token.clone());
+        merged.extend(value.cloned());
+    }
+
+    merged.extend(overrides.iter().cloned());
+    if human && !merged.iter().any(|a| a == "-H" || a == "--human") {
+        merged.push("-H".to_string());
+    }
+    merged
+}
+
+/// ID of the option a token names (`--size`, `--size=20`, `-n`), and whether
+/// the option takes a value. Global options are looked up on the top command.
+fn option_of(cli: &Command, command: Option<&Command>, token: &str) -> Option<(String, bool)> {
+    let matches = |arg: &&Arg| {
+        if let Some(long) = token.strip_prefix("--") {
+            let long = long.split('=').next().unwrap_or(long);
+            arg.get_long() == Some(long)
+                || arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&long))
+        } else if let Some(short) = token.strip_prefix('-').filter(|s| s.chars().count() == 1) {
+            arg.get_short().map(|c| c.to_string()).as_deref() == Some(short)
+        } else {
+            false
+        }
+    };
+
+    command
+        .into_iter()
+        .chain(std::iter::once(cli))
+        .find_map(|command| command.get_arguments().find(matches))
+        .map(|arg| (arg.get_id().to_string(), arg.get_action().takes_values()))
+}
+
+fn format_saved_human(config: &Config) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("{:<30} {}\n", "NAME", "COMMAND"));
+    output.push_str(&"-".repeat(80));
+    output.push('\n');
+
+    for (name, args) in &config.saved {
+        output.push_str(&format!("{:<30} {}\n", name, shell_join(args)));
+    }
+
+    output
+}
+
+/// Join arguments into a copy-pasteable shell command line
+pub fn shell_join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let safe = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.,:/@=+%".contains(c));
+            if safe {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,24 +1,35 @@
-use crate::client::EsClient;
-use crate::format::format_output;
+use crate::client::{read_body, EsClient};
+use crate::commands::async_query;
+use crate::format::{format_output, format_profile};
 
-pub async fn run(index: &str, query: &str, human: bool) -> Result<(), String> {
+pub async fn run(
+    index: &str,
+    query: &str,
+    profile: bool,
+    run_async: bool,
+    human: bool,
+) -> Result<(), String> {
     // Validate JSON before sending
-    serde_json::from_str::<serde_json::Value>(query)
+    let mut request = serde_json::from_str::<serde_json::Value>(query)
         .map_err(|e| format!("Invalid JSON query: {e}"))?;
+    if profile {
+        request
+            .as_object_mut()
+            .ok_or("Query must be a JSON object")?
+            .insert("profile".to_string(), serde_json::json!(true));
+    }
 
     let client = EsClient::new()?;
     let path = format!("/{}/_search", index);
-    let response = client.post(&path, query).await?;
-
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = if run_async {
+        async_query::search(&client, index, &request).await?
+    } else {
+        let response = client.post(&path, &request.to_string()).await?;
+        read_body(response).await?
+    };
     println!("{}", format_output(&body, human));
+    if profile && human {
+        println!("{}", format_profile(&body));
+    }
     Ok(())
 }
@@ -0,0 +1,111 @@
+use crate::client::{read_body, EsClient};
+use crate::format::format_output;
+use crate::query::parse_query;
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Options for SQL queries
+pub struct SqlOptions<'a> {
+    pub query: &'a str,
+    pub params: &'a [String],
+    pub filter: Option<&'a str>,
+    pub time_zone: Option<&'a str>,
+    pub fetch_size: usize,
+    pub format: Option<&'a str>,
+    pub translate: bool,
+}
+
+pub async fn run(opts: SqlOptions<'_>, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let mut body = json!({ "query": opts.query });
+
+    // Values for ? placeholders, in order
+    if !opts.params.is_empty() {
+        let params: Vec<Value> = opts
+            .params
+            .iter()
+            .map(|p| serde_json::from_str(p).unwrap_or_else(|_| json!(p)))
+            .collect();
+        body["params"] = json!(params);
+    }
+    if let Some(filter) = opts.filter {
+        body["filter"] = parse_query(filter)?;
+    }
+    if let Some(time_zone) = opts.time_zone {
+        body["time_zone"] = json!(time_zone);
+    }
+
+    if opts.translate {
+        let response = client.post("/_sql/translate", &body.to_string()).await?;
+        let body = read_body(response).await?;
+        println!("{}", format_output(&body, human));
+        return Ok(());
+    }
+
+    body["fetch_size"] = json!(opts.fetch_size);
+
+    match opts.format {
+        Some(format) => fetch_text(&client, body, format).await,
+        None => fetch_json(&client, body, human).await,
+    }
+}
+
+/// Follow the cursor until all rows are fetched, then print them as one response
+async fn fetch_json(client: &EsClient, body: Value, human: bool) -> Result<(), String> {
+    let response = client.post("/_sql?format=json", &body.to_string()).await?;
+    let mut page: Value =
+        serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+    let columns = page.get("columns").cloned().unwrap_or(json!([]));
+    let mut rows: Vec<Value> = Vec::new();
+
+    loop {
+        if let Some(Value::Array(page_rows)) = page.get_mut("rows").map(Value::take) {
+            rows.extend(page_rows);
+        }
+        let Some(cursor) = page.get("cursor").and_then(|c| c.as_str()) else {
+            break;
+        };
+        let request = json!({ "cursor": cursor });
+        let response = client
+            .post("/_sql?format=json", &request.to_string())
+            .await?;
+        page = serde_json::from_str(&read_body(response).await?).map_err(|e| e.to_string())?;
+    }
+
+    if human {
+        // Same table as ES|QL results
+        let table = json!({ "columns": columns, "values": rows });
+        println!("{}", format_output(&table.to_string(), true));
+    } else {
+        println!("{}", json!({ "columns": columns, "rows": rows }));
+    }
+    Ok(())
+}
+
+/// Server-side text formats: print each page as it comes. The cursor of text
+/// formats is in the Cursor response header.
+async fn fetch_text(client: &EsClient, body: Value, format: &str) -> Result<(), String> {
+    let path = format!("/_sql?format={}", format);
+    let mut request = body;
+    let mut stdout = std::io::stdout();
+
+    loop {
+        let response = client.post(&path, &request.to_string()).await?;
+        let cursor = response
+            .headers()
+            .get("Cursor")
+            .and_then(|c| c.to_str().ok())
+            .filter(|c| !c.is_empty())
+            .map(String::from);
+        let page = read_body(response).await?;
+        stdout
+            .write_all(page.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        match cursor {
+            Some(cursor) => request = json!({ "cursor": cursor }),
+            None => break,
+        }
+    }
+    stdout.flush().map_err(|e| e.to_string())
+}
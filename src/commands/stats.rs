@@ -1,94 +1,266 @@
-use crate::client::EsClient;
+use crate::client::{read_body, EsClient};
+use crate::format::{format_table, format_value};
+use crate::query::parse_optional_query;
+use crate::timerange::TimeArgs;
+use serde_json::{json, Map, Value};
 
-pub async fn run(index: &str, field: &str, human: bool) -> Result<(), String> {
+/// Field types `extended_stats` and friends can aggregate
+const NUMERIC_TYPES: [&str; 9] = [
+    "long",
+    "integer",
+    "short",
+    "byte",
+    "double",
+    "float",
+    "half_float",
+    "scaled_float",
+    "unsigned_long",
+];
+
+pub async fn run(
+    index: &str,
+    fields: &str,
+    query: Option<&str>,
+    time: &TimeArgs,
+    timestamp_field: &str,
+    percentiles: Option<&str>,
+    human: bool,
+) -> Result<(), String> {
+    let fields: Vec<&str> = fields
+        .split(',')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .collect();
+    if fields.is_empty() {
+        return Err("No field given".to_string());
+    }
+    let percents = percentiles.map(parse_percentiles).transpose()?;
+    let range = time.resolve()?;
+    let filter = parse_optional_query(query)?;
     let client = EsClient::new()?;
 
+    check_numeric(&client, index, &fields).await?;
+
+    // A single field keeps the plain aggregation names, several fields are
+    // told apart by a "field:" prefix
+    let multi = fields.len() > 1;
+    let mut aggs = Map::new();
+    for field in &fields {
+        aggs.insert(
+            agg_name("stats", field, multi),
+            json!({ "extended_stats": { "field": field } }),
+        );
+        aggs.insert(
+            agg_name("cardinality", field, multi),
+            json!({ "cardinality": { "field": field } }),
+        );
+        aggs.insert(
+            agg_name("median_absolute_deviation", field, multi),
+            json!({ "median_absolute_deviation": { "field": field } }),
+        );
+        if let Some(percents) = &percents {
+            aggs.insert(
+                agg_name("percentiles", field, multi),
+                json!({ "percentiles": { "field": field, "percents": percents, "keyed": false } }),
+            );
+        }
+    }
+
     let path = format!("/{}/_search", index);
 
-    let query = serde_json::json!({
+    let query = json!({
         "size": 0,
-        "aggs": {
-            "stats": {
-                "extended_stats": {
-                    "field": field
-                }
-            }
-        }
+        "query": range.apply(filter, timestamp_field),
+        "aggs": aggs
     });
 
     let response = client.post(&path, &query.to_string()).await?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
 
     if human {
-        println!("{}", format_stats_human(&body));
+        println!(
+            "{}",
+            format_stats_human(&body, &fields, percents.as_deref().unwrap_or(&[]))
+        );
     } else {
         println!("{}", body);
     }
     Ok(())
 }
 
-fn format_stats_human(json: &str) -> String {
-    let value: serde_json::Value = match serde_json::from_str(json) {
+fn agg_name(kind: &str, field: &str, multi: bool) -> String {
+    if multi {
+        format!("{}:{}", field, kind)
+    } else {
+        kind.to_string()
+    }
+}
+
+fn parse_percentiles(spec: &str) -> Result<Vec<f64>, String> {
+    spec.split(',')
+        .map(|p| {
+            p.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|p| (0.0..=100.0).contains(p))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid percentile \"{}\": expected numbers between 0 and 100 (e.g., 50,90,99)",
+                        p.trim()
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Look up the fields in the mapping and fail early on missing or non-numeric
+/// fields, instead of the aggregation error or empty result Elasticsearch gives.
+/// Skipped when the mapping cannot be read (e.g., no view_index_metadata).
+async fn check_numeric(client: &EsClient, index: &str, fields: &[&str]) -> Result<(), String> {
+    let path = format!("/{}/_mapping/field/{}", index, fields.join(","));
+    let response = client.get(&path).await?;
+    if !response.status().is_success() {
+        return Ok(());
+    }
+    let Ok(value) = response
+        .text()
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|body| serde_json::from_str::<Value>(&body).map_err(|e| e.to_string()))
+    else {
+        return Ok(());
+    };
+    let Some(indices) = value.as_object() else {
+        return Ok(());
+    };
+
+    for field in fields {
+        // Types of the field in each index where it is mapped
+        let mut types: Vec<(&str, &str)> = Vec::new();
+        for (name, data) in indices {
+            let field_type = data
+                .get("mappings")
+                .and_then(|m| m.get(*field))
+                .and_then(|m| m.get("mapping"))
+                .and_then(|m| m.as_object())
+                .and_then(|m| m.values().next())
+                .and_then(|m| m.get("type"))
+                .and_then(|t| t.as_str());
+            if let Some(field_type) = field_type {
+                types.push((name, field_type));
+            }
+        }
+
+        if types.is_empty() {
+            return Err(format!(
+                "Field \"{}\" not found in {} (see: es-cli fields {})",
+                field, index, index
+            ));
+        }
+        if let Some((name, field_type)) = types.iter().find(|(_, t)| !NUMERIC_TYPES.contains(t)) {
+            let hint = if *field_type == "keyword" {
+                format!(" (try: es-cli values {} {})", index, field)
+            } else {
+                String::new()
+            };
+            return Err(format!(
+                "Field \"{}\" is not numeric: mapped as {} in {}{}",
+                field, field_type, name, hint
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn format_stats_human(json: &str, fields: &[&str], percents: &[f64]) -> String {
+    let value: Value = match serde_json::from_str(json) {
         Ok(v) => v,
         Err(_) => return json.to_string(),
     };
+    let aggregations = value.get("aggregations").cloned().unwrap_or(json!({}));
+    let multi = fields.len() > 1;
 
-    let mut output = String::new();
-
-    if let Some(stats) = value.get("aggregations").and_then(|a| a.get("stats")) {
-        let count = stats.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
-        let min = stats.get("min").and_then(|m| m.as_f64());
-        let max = stats.get("max").and_then(|m| m.as_f64());
-        let avg = stats.get("avg").and_then(|a| a.as_f64());
-        let sum = stats.get("sum").and_then(|s| s.as_f64());
-        let std_dev = stats.get("std_deviation").and_then(|s| s.as_f64());
-
-        output.push_str(&format!("{:<20} {}\n", "Count:", count));
-        output.push_str(&format!(
-            "{:<20} {}\n",
-            "Min:",
-            min.map(format_number).unwrap_or_else(|| "-".to_string())
-        ));
-        output.push_str(&format!(
-            "{:<20} {}\n",
-            "Max:",
-            max.map(format_number).unwrap_or_else(|| "-".to_string())
-        ));
-        output.push_str(&format!(
-            "{:<20} {}\n",
-            "Average:",
-            avg.map(format_number).unwrap_or_else(|| "-".to_string())
-        ));
-        output.push_str(&format!(
-            "{:<20} {}\n",
-            "Sum:",
-            sum.map(format_number).unwrap_or_else(|| "-".to_string())
-        ));
-        output.push_str(&format!(
-            "{:<20} {}\n",
-            "Std Deviation:",
-            std_dev
-                .map(format_number)
-                .unwrap_or_else(|| "-".to_string())
-        ));
-    }
+    let mut labels: Vec<String> = [
+        "Count",
+        "Min",
+        "Max",
+        "Average",
+        "Sum",
+        "Std Deviation",
+        "Distinct",
+        "Median Abs Dev",
+    ]
+    .iter()
+    .map(|l| l.to_string())
+    .collect();
+    labels.extend(percents.iter().map(|p| format!("p{}", p)));
 
-    output
-}
+    let rows: Vec<Vec<Value>> = fields
+        .iter()
+        .map(|field| {
+            let agg = |kind: &str| aggregations.get(agg_name(kind, field, multi));
+            let stat = |key: &str| {
+                agg("stats")
+                    .and_then(|s| s.get(key))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            };
+            let single = |kind: &str| {
+                agg(kind)
+                    .and_then(|a| a.get("value"))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            };
 
-fn format_number(n: f64) -> String {
-    if n.fract() == 0.0 && n.abs() < 1e15 {
-        format!("{}", n as i64)
-    } else {
-        format!("{:.2}", n)
+            let mut row = vec![
+                stat("count"),
+                stat("min"),
+                stat("max"),
+                stat("avg"),
+                stat("sum"),
+                stat("std_deviation"),
+                single("cardinality"),
+                single("median_absolute_deviation"),
+            ];
+            let values = agg("percentiles")
+                .and_then(|p| p.get("values"))
+                .and_then(|v| v.as_array());
+            row.extend((0..percents.len()).map(|i| {
+                values
+                    .and_then(|v| v.get(i))
+                    .and_then(|v| v.get("value"))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            }));
+            row
+        })
+        .collect();
+
+    if !multi {
+        // One field: one line per statistic
+        let mut output = String::new();
+        for (label, value) in labels.iter().zip(&rows[0]) {
+            output.push_str(&format!(
+                "{:<20} {}\n",
+                format!("{}:", label),
+                format_value(value)
+            ));
+        }
+        return output;
     }
+
+    // Several fields: one row per field
+    let mut columns = vec!["field".to_string()];
+    columns.extend(labels);
+    let rows: Vec<Vec<Value>> = fields
+        .iter()
+        .zip(rows)
+        .map(|(field, row)| {
+            let mut full = vec![json!(field)];
+            full.extend(row);
+            full
+        })
+        .collect();
+    format_table(&columns, &rows)
 }
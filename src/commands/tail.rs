@@ -1,7 +1,15 @@
-use crate::client::EsClient;
-use crate::format::format_output;
+use crate::client::{read_body, EsClient};
+use crate::format::{format_groups, format_output};
+use crate::group::{self, parse_groups, GroupBy};
+use serde_json::Value;
 
-pub async fn run(index: &str, size: usize, human: bool) -> Result<(), String> {
+pub async fn run(
+    index: &str,
+    size: usize,
+    group_by: Option<&str>,
+    per_group: usize,
+    human: bool,
+) -> Result<(), String> {
     let client = EsClient::new()?;
 
     let path = format!("/{}/_search", index);
@@ -17,17 +25,22 @@ pub async fn run(index: &str, size: usize, human: bool) -> Result<(), String> {
         }
     });
 
-    let response = client.post(&path, &query.to_string()).await?;
-
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
+    // Most recent documents for each value of the field
+    if let Some(field) = group_by {
+        let group = GroupBy { field, per_group };
+        let body = group::search(&client, &path, &query, &group).await?;
+        if human {
+            let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+            println!("{}", format_groups(field, &parse_groups(&value, field)));
+        } else {
+            println!("{}", body);
+        }
+        return Ok(());
     }
 
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let response = client.post(&path, &query.to_string()).await?;
+
+    let body = read_body(response).await?;
     println!("{}", format_output(&body, human));
     Ok(())
 }
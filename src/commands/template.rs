@@ -0,0 +1,127 @@
+use crate::client::{read_body, EsClient};
+use crate::format::{format_output, truncate};
+use crate::query::parse_params;
+use serde_json::{json, Value};
+
+/// List stored mustache templates (GET /_cluster/state/metadata)
+pub async fn list(human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let response = client
+        .get("/_cluster/state/metadata?filter_path=metadata.stored_scripts")
+        .await?;
+
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    // Stored scripts also hold painless scripts, only keep search templates
+    let templates: serde_json::Map<String, Value> = value
+        .pointer("/metadata/stored_scripts")
+        .and_then(|s| s.as_object())
+        .map(|scripts| {
+            scripts
+                .iter()
+                .filter(|(_, script)| {
+                    script.get("lang").and_then(|l| l.as_str()) == Some("mustache")
+                })
+                .map(|(id, script)| (id.clone(), script.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if human {
+        println!("{}", format_templates_human(&templates));
+    } else {
+        println!("{}", Value::Object(templates));
+    }
+    Ok(())
+}
+
+pub async fn get(id: &str, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let response = client.get(&format!("/_scripts/{}", id)).await?;
+
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+/// Store a template. The source is JSON or raw mustache text, inline or read from @file.
+pub async fn put(id: &str, source: &str, human: bool) -> Result<(), String> {
+    let source = match source.strip_prefix('@') {
+        Some(file) => {
+            std::fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?
+        }
+        None => source.to_string(),
+    };
+    // Mustache sections often make the source invalid JSON, send those as a string
+    let source = serde_json::from_str::<Value>(&source)
+        .ok()
+        .filter(|v| v.is_object())
+        .unwrap_or(Value::String(source));
+
+    let client = EsClient::new()?;
+    let body = json!({ "script": { "lang": "mustache", "source": source } });
+    let response = client
+        .put(&format!("/_scripts/{}", id), &body.to_string())
+        .await?;
+
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+pub async fn delete(id: &str, human: bool) -> Result<(), String> {
+    let client = EsClient::new()?;
+    let response = client.delete(&format!("/_scripts/{}", id)).await?;
+
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+/// Show the query a template produces with the given parameters (POST /_render/template)
+pub async fn render(id: &str, params: &[String], human: bool) -> Result<(), String> {
+    let body = json!({ "id": id, "params": parse_params(params)? });
+
+    let client = EsClient::new()?;
+    let response = client.post("/_render/template", &body.to_string()).await?;
+
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+/// Search with a stored template (POST /<index>/_search/template)
+pub async fn run(index: &str, id: &str, params: &[String], human: bool) -> Result<(), String> {
+    let body = json!({ "id": id, "params": parse_params(params)? });
+
+    let client = EsClient::new()?;
+    let path = format!("/{}/_search/template", index);
+    let response = client.post(&path, &body.to_string()).await?;
+
+    let body = read_body(response).await?;
+    println!("{}", format_output(&body, human));
+    Ok(())
+}
+
+fn format_templates_human(templates: &serde_json::Map<String, Value>) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("{:<40} {}\n", "ID", "SOURCE"));
+    output.push_str(&"-".repeat(100));
+    output.push('\n');
+
+    for (id, script) in templates {
+        let source = match script.get("source") {
+            Some(Value::String(s)) => s.split_whitespace().collect::<Vec<_>>().join(" "),
+            Some(other) => other.to_string(),
+            None => "-".to_string(),
+        };
+        output.push_str(&format!(
+            "{:<40} {}\n",
+            truncate(id, 40),
+            truncate(&source, 60)
+        ));
+    }
+
+    output
+}
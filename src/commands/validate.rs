@@ -0,0 +1,65 @@
+use crate::client::{read_body, EsClient};
+use crate::query::parse_query;
+use serde_json::{json, Value};
+
+pub async fn run(index: &str, query: &str, all_shards: bool, human: bool) -> Result<(), String> {
+    let query = parse_query(query)?;
+
+    let client = EsClient::new()?;
+    let mut path = format!("/{}/_validate/query?explain=true&rewrite=true", index);
+    if all_shards {
+        path.push_str("&all_shards=true");
+    }
+    let response = client
+        .post(&path, &json!({ "query": query }).to_string())
+        .await?;
+
+    let body = read_body(response).await?;
+    let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    if human {
+        println!("{}", format_validate_human(&value));
+    } else {
+        println!("{}", body);
+    }
+
+    // Invalid queries fail the command so scripts can rely on the exit status
+    if value.get("valid").and_then(|v| v.as_bool()) == Some(false) {
+        return Err("Query is not valid".to_string());
+    }
+    Ok(())
+}
+
+fn format_validate_human(value: &Value) -> String {
+    let mut output = String::new();
+
+    let valid = value
+        .get("valid")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    output.push_str(&format!("Valid: {}\n", if valid { "yes" } else { "no" }));
+
+    if let Some(explanations) = value.get("explanations").and_then(|e| e.as_array()) {
+        for explanation in explanations {
+            let index = explanation
+                .get("index")
+                .and_then(|i| i.as_str())
+                .unwrap_or("-");
+            output.push_str(&format!("\n--- {}", index));
+            if let Some(shard) = explanation.get("shard").and_then(|s| s.as_i64()) {
+                output.push_str(&format!(" [shard {}]", shard));
+            }
+            output.push_str(" ---\n");
+
+            if let Some(text) = explanation.get("explanation").and_then(|e| e.as_str()) {
+                output.push_str(text);
+                output.push('\n');
+            }
+            if let Some(error) = explanation.get("error").and_then(|e| e.as_str()) {
+                output.push_str(&format!("Error: {}\n", error));
+            }
+        }
+    }
+
+    output
+}
@@ -1,33 +1,100 @@
-use crate::client::EsClient;
+use crate::client::{read_body, EsClient};
+use crate::format::{csv_line, format_csv_rows, truncate};
+use crate::query::{parse_optional_query, parse_query};
+use crate::timerange::TimeArgs;
+use serde_json::{json, Value};
+
+/// Options for the values command
+pub struct ValuesOptions<'a> {
+    pub index: &'a str,
+    pub field: &'a str,
+    pub query: Option<&'a str>,
+    pub size: usize,
+    pub missing: bool,
+    pub include: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    pub order: Option<&'a str>,
+    pub min_count: Option<u64>,
+    pub significant: bool,
+    pub background: Option<&'a str>,
+    pub rare: bool,
+    pub max_count: u64,
+    pub all: bool,
+    pub csv: bool,
+    pub time: &'a TimeArgs,
+    pub timestamp_field: &'a str,
+}
+
+pub async fn run(opts: ValuesOptions<'_>, human: bool) -> Result<(), String> {
+    let range = opts.time.resolve()?;
+    let filter = parse_optional_query(opts.query)?;
+
+    if opts.all {
+        return run_all(&opts, range.apply(filter, opts.timestamp_field), human).await;
+    }
+    if opts.field.contains(',') {
+        return Err("Several fields can only be combined with --all".to_string());
+    }
+
+    let mut agg = if opts.rare {
+        // Least frequent terms, up to max_doc_count occurrences
+        json!({ "rare_terms": { "field": opts.field, "max_doc_count": opts.max_count } })
+    } else if opts.significant {
+        // Terms unusually frequent in the matching documents compared to the background.
+        // The background keeps the time range so "errors vs all traffic" compares the same period.
+        let mut agg = json!({ "significant_terms": { "field": opts.field, "size": opts.size } });
+        if opts.background.is_some() || !range.is_empty() {
+            let background = match opts.background {
+                Some(b) => parse_query(b)?,
+                None => json!({ "match_all": {} }),
+            };
+            agg["significant_terms"]["background_filter"] =
+                range.apply(background, opts.timestamp_field);
+        }
+        agg
+    } else {
+        let mut agg = json!({ "terms": { "field": opts.field, "size": opts.size } });
+        if let Some(order) = opts.order {
+            agg["terms"]["order"] = parse_order(order)?;
+        }
+        agg
+    };
+
+    let kind = if opts.rare {
+        "rare_terms"
+    } else if opts.significant {
+        "significant_terms"
+    } else {
+        "terms"
+    };
+    if let Some(min_count) = opts.min_count {
+        agg[kind]["min_doc_count"] = json!(min_count);
+    }
+    if let Some(include) = opts.include {
+        agg[kind]["include"] = json!(include);
+    }
+    if let Some(exclude) = opts.exclude {
+        agg[kind]["exclude"] = json!(exclude);
+    }
+
+    let mut aggs = json!({ "values": agg });
+    if opts.missing {
+        aggs["missing"] = json!({ "missing": { "field": opts.field } });
+    }
 
-pub async fn run(index: &str, field: &str, size: usize, human: bool) -> Result<(), String> {
     let client = EsClient::new()?;
 
-    let path = format!("/{}/_search", index);
+    let path = format!("/{}/_search", opts.index);
 
-    let query = serde_json::json!({
+    let query = json!({
         "size": 0,
-        "aggs": {
-            "values": {
-                "terms": {
-                    "field": field,
-                    "size": size
-                }
-            }
-        }
+        "query": range.apply(filter, opts.timestamp_field),
+        "aggs": aggs
     });
 
     let response = client.post(&path, &query.to_string()).await?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "HTTP {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
-    }
-
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = read_body(response).await?;
 
     if human {
         println!("{}", format_values_human(&body));
@@ -37,23 +104,183 @@ pub async fn run(index: &str, field: &str, size: usize, human: bool) -> Result<(
     Ok(())
 }
 
+/// Number of composite buckets fetched per request with --all
+const PAGE_SIZE: usize = 1000;
+
+/// Enumerate every distinct value (or tuple of values across comma-separated
+/// fields) by paging through a composite aggregation, printing rows as pages arrive
+async fn run_all(opts: &ValuesOptions<'_>, query: Value, human: bool) -> Result<(), String> {
+    let fields: Vec<&str> = opts.field.split(',').map(|f| f.trim()).collect();
+    let sources: Vec<Value> = fields
+        .iter()
+        .map(|f| json!({ *f: { "terms": { "field": f, "missing_bucket": opts.missing } } }))
+        .collect();
+
+    let client = EsClient::new()?;
+    let path = format!("/{}/_search", opts.index);
+
+    let width = if fields.len() == 1 { 60 } else { 30 };
+    if opts.csv {
+        let header = fields
+            .iter()
+            .map(|f| f.to_string())
+            .chain(["count".to_string()]);
+        print!("{}", csv_line(header));
+    } else if human {
+        let mut header: String = fields
+            .iter()
+            .map(|f| format!("{:<width$} ", f.to_uppercase(), width = width))
+            .collect();
+        header.push_str(&format!("{:>15}", "COUNT"));
+        println!("{}", header);
+        println!("{}", "-".repeat(header.len()));
+    }
+
+    let mut after: Option<Value> = None;
+    let mut total = 0;
+    loop {
+        let mut composite = json!({ "size": PAGE_SIZE, "sources": sources });
+        if let Some(after) = &after {
+            composite["after"] = after.clone();
+        }
+        let body = json!({
+            "size": 0,
+            "query": query,
+            "aggs": { "values": { "composite": composite } }
+        });
+
+        let response = client.post(&path, &body.to_string()).await?;
+        let body = read_body(response).await?;
+        let value: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+        let agg = value.get("aggregations").and_then(|a| a.get("values"));
+        let buckets = agg
+            .and_then(|a| a.get("buckets"))
+            .and_then(|b| b.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let rows: Vec<Vec<Value>> = buckets
+            .iter()
+            .map(|bucket| {
+                let mut row: Vec<Value> = fields
+                    .iter()
+                    .map(|f| {
+                        bucket
+                            .get("key")
+                            .and_then(|k| k.get(*f))
+                            .cloned()
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect();
+                row.push(bucket.get("doc_count").cloned().unwrap_or(Value::Null));
+                row
+            })
+            .collect();
+        total += rows.len();
+
+        if opts.csv {
+            print!("{}", format_csv_rows(&rows));
+        } else if human {
+            for row in &rows {
+                let (count, keys) = row.split_last().unwrap_or((&Value::Null, &[]));
+                let mut line: String = keys
+                    .iter()
+                    .map(|k| {
+                        let key = match k {
+                            Value::Null => "(missing)".to_string(),
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        };
+                        format!("{:<width$} ", truncate(&key, width), width = width)
+                    })
+                    .collect();
+                line.push_str(&format!("{:>15}", count.to_string()));
+                println!("{}", line);
+            }
+        } else {
+            // One JSON object per line so large exports can be streamed
+            for row in &rows {
+                let item: serde_json::Map<String, Value> = fields
+                    .iter()
+                    .map(|f| f.to_string())
+                    .chain(["count".to_string()])
+                    .zip(row.iter().cloned())
+                    .collect();
+                println!("{}", Value::Object(item));
+            }
+        }
+
+        after = agg.and_then(|a| a.get("after_key")).cloned();
+        if buckets.len() < PAGE_SIZE || after.is_none() {
+            break;
+        }
+    }
+
+    if human && !opts.csv {
+        println!("\n({} distinct values)", total);
+    }
+    Ok(())
+}
+
+/// Terms order: count or key, optionally with :asc/:desc, or just asc/desc on the count
+fn parse_order(order: &str) -> Result<Value, String> {
+    let (by, direction) = match order.split_once(':') {
+        Some((by, direction)) => (by, Some(direction)),
+        None if order == "asc" || order == "desc" => ("count", Some(order)),
+        None => (order, None),
+    };
+    let (key, default_direction) = match by {
+        "count" => ("_count", "desc"),
+        "key" => ("_key", "asc"),
+        _ => {
+            return Err(format!(
+                "Invalid order \"{}\": expected count, key, asc or desc",
+                order
+            ))
+        }
+    };
+    let direction = direction.unwrap_or(default_direction);
+    if direction != "asc" && direction != "desc" {
+        return Err(format!(
+            "Invalid order direction \"{}\": expected asc or desc",
+            direction
+        ));
+    }
+    Ok(json!({ key: direction }))
+}
+
 fn format_values_human(json: &str) -> String {
-    let value: serde_json::Value = match serde_json::from_str(json) {
+    let value: Value = match serde_json::from_str(json) {
         Ok(v) => v,
         Err(_) => return json.to_string(),
     };
 
-    let mut output = String::new();
-    output.push_str(&format!("{:<60} {:>15}\n", "VALUE", "COUNT"));
-    output.push_str(&"-".repeat(77));
-    output.push('\n');
-
-    if let Some(buckets) = value
+    let buckets = value
         .get("aggregations")
         .and_then(|a| a.get("values"))
         .and_then(|v| v.get("buckets"))
-        .and_then(|b| b.as_array())
-    {
+        .and_then(|b| b.as_array());
+
+    // significant_terms buckets also carry a background count and a score
+    let significant = buckets
+        .and_then(|b| b.first())
+        .is_some_and(|b| b.get("score").is_some());
+
+    let mut output = String::new();
+    if significant {
+        output.push_str(&format!(
+            "{:<60} {:>15} {:>15} {:>10}\n",
+            "VALUE", "COUNT", "BG COUNT", "SCORE"
+        ));
+        output.push_str(&"-".repeat(103));
+    } else {
+        output.push_str(&format!("{:<60} {:>15}\n", "VALUE", "COUNT"));
+        output.push_str(&"-".repeat(77));
+    }
+    output.push('\n');
+
+    if let Some(buckets) = buckets {
         for bucket in buckets {
             let key = bucket
                 .get("key")
@@ -70,7 +297,29 @@ fn format_values_human(json: &str) -> String {
                 .and_then(|c| c.as_u64())
                 .unwrap_or(0);
 
-            output.push_str(&format!("{:<60} {:>15}\n", truncate(&key, 60), count));
+            if significant {
+                let bg_count = bucket.get("bg_count").and_then(|c| c.as_u64()).unwrap_or(0);
+                let score = bucket.get("score").and_then(|s| s.as_f64()).unwrap_or(0.0);
+                output.push_str(&format!(
+                    "{:<60} {:>15} {:>15} {:>10.3}\n",
+                    truncate(&key, 60),
+                    count,
+                    bg_count,
+                    score
+                ));
+            } else {
+                output.push_str(&format!("{:<60} {:>15}\n", truncate(&key, 60), count));
+            }
+        }
+
+        // Documents without the field (--missing)
+        if let Some(missing) = value
+            .get("aggregations")
+            .and_then(|a| a.get("missing"))
+            .and_then(|m| m.get("doc_count"))
+            .and_then(|c| c.as_u64())
+        {
+            output.push_str(&format!("{:<60} {:>15}\n", "(missing)", missing));
         }
 
         // Show if there are more values
@@ -88,11 +337,3 @@ fn format_values_human(json: &str) -> String {
 
     output
 }
-
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max - 3])
-    }
-}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Persistent CLI configuration, stored as JSON in the config directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Saved command invocations, keyed by name (arguments exclude the binary name)
+    #[serde(default)]
+    pub saved: BTreeMap<String, Vec<String>>,
+
+    /// Named cluster connections, keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A cluster connection. Without credentials, those of the environment are used.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Directory holding es-cli state: $ES_CLI_HOME, $XDG_CONFIG_HOME/es-cli or ~/.config/es-cli
+pub fn config_dir() -> Result<PathBuf, String> {
+    if let Ok(dir) = env::var("ES_CLI_HOME") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(dir).join("es-cli"));
+    }
+    let home = env::var("HOME").map_err(|_| "Cannot locate config directory: HOME not set")?;
+    Ok(PathBuf::from(home).join(".config").join("es-cli"))
+}
+
+impl Config {
+    fn path() -> Result<PathBuf, String> {
+        Ok(config_dir()?.join("config.json"))
+    }
+
+    /// Load the config file, returning an empty config if it does not exist yet
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, content + "\n")
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
@@ -105,7 +105,101 @@ fn format_search(hits: &Value) -> String {
     output
 }
 
-fn format_source(value: &Value, indent: usize) -> String {
+/// Grouped documents, listed under each distinct value of `field`
+pub fn format_groups(field: &str, groups: &[(Value, Vec<Value>)]) -> String {
+    let mut output = format!("Groups: {}\n\n", groups.len());
+
+    for (key, docs) in groups {
+        output.push_str(&format!(
+            "=== {}: {} ({}) ===\n",
+            field,
+            format_value(key),
+            docs.len()
+        ));
+        output.push_str(&format_search(&serde_json::json!({ "hits": docs })));
+    }
+
+    output
+}
+
+/// EQL results: matching events, or sequences as blocks of time-ordered events
+pub fn format_eql(json: &str, timestamp_field: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return json.to_string();
+    };
+    let hits = &value["hits"];
+    let mut output = String::new();
+
+    let total = hits
+        .pointer("/total/value")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let relation = hits
+        .pointer("/total/relation")
+        .and_then(|r| r.as_str())
+        .unwrap_or("eq");
+    let prefix = if relation == "gte" { "≥" } else { "" };
+
+    if let Some(sequences) = hits.get("sequences").and_then(|s| s.as_array()) {
+        output.push_str(&format!("Total: {}{} sequences\n\n", prefix, total));
+        for (i, sequence) in sequences.iter().enumerate() {
+            output.push_str(&format!("=== Sequence {}", i + 1));
+            if let Some(keys) = sequence.get("join_keys").and_then(|k| k.as_array()) {
+                let keys: Vec<String> = keys.iter().map(format_value).collect();
+                output.push_str(&format!(" [join: {}]", keys.join(", ")));
+            }
+            output.push_str(" ===\n");
+            if let Some(events) = sequence.get("events").and_then(|e| e.as_array()) {
+                output.push_str(&format_eql_events(events, timestamp_field));
+            }
+        }
+    } else if let Some(events) = hits.get("events").and_then(|e| e.as_array()) {
+        output.push_str(&format!("Total: {}{} events\n\n", prefix, total));
+        output.push_str(&format_eql_events(events, timestamp_field));
+    }
+
+    output
+}
+
+fn format_eql_events(events: &[Value], timestamp_field: &str) -> String {
+    let mut output = String::new();
+
+    for (i, event) in events.iter().enumerate() {
+        // Sequences with missing events (`!` in the query) keep a placeholder
+        if event.get("missing").and_then(|m| m.as_bool()) == Some(true) {
+            output.push_str(&format!("--- [{}] (missing event) ---\n\n", i + 1));
+            continue;
+        }
+
+        let source = event.get("_source").unwrap_or(&Value::Null);
+        let timestamp = source_field(source, timestamp_field)
+            .map(format_value)
+            .unwrap_or_else(|| "-".to_string());
+        output.push_str(&format!(
+            "--- [{}] {} ({}/{}) ---\n",
+            i + 1,
+            timestamp,
+            event.get("_index").and_then(|i| i.as_str()).unwrap_or(""),
+            event.get("_id").and_then(|i| i.as_str()).unwrap_or("")
+        ));
+        output.push_str(&format_source(source, 0));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Value of a dotted field in a document source, whether stored flat or nested
+fn source_field<'a>(source: &'a Value, field: &str) -> Option<&'a Value> {
+    if let Some(value) = source.get(field) {
+        return Some(value);
+    }
+    let (head, rest) = field.split_once('.')?;
+    source_field(source.get(head)?, rest)
+}
+
+/// Document source as indented `key: value` lines
+pub fn format_source(value: &Value, indent: usize) -> String {
     let prefix = "  ".repeat(indent);
     let mut output = String::new();
 
@@ -166,7 +260,7 @@ fn format_list(value: &Value) -> String {
     output
 }
 
-fn format_value(value: &Value) -> String {
+pub fn format_value(value: &Value) -> String {
     match value {
         Value::Null => "-".to_string(),
         Value::String(s) => s.clone(),
@@ -190,12 +284,105 @@ fn format_value(value: &Value) -> String {
     }
 }
 
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
+/// Shorten text to at most `max` characters, ending with "..." when cut
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
         s.to_string()
+    } else if max < 3 {
+        s.chars().take(max).collect()
     } else {
-        format!("{}...", &s[..max - 3])
+        let cut: String = s.chars().take(max - 3).collect();
+        format!("{}...", cut)
+    }
+}
+
+/// Render rows as an aligned table, each column as wide as its widest cell
+pub fn format_table(columns: &[String], rows: &[Vec<Value>]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(format_value).collect())
+        .collect();
+
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .chain(std::iter::once(column.chars().count()))
+                .max()
+                .unwrap_or(0)
+                .min(60)
+        })
+        .collect();
+
+    let mut output = String::new();
+    let header: Vec<String> = columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, width)| format!("{:<width$}", column.to_uppercase(), width = width))
+        .collect();
+    output.push_str(header.join("  ").trim_end());
+    output.push('\n');
+    output.push_str(&"-".repeat(widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1)));
+    output.push('\n');
+
+    for (row, values) in cells.iter().zip(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((cell, value), width)| {
+                let cell = truncate(cell, *width);
+                // Numbers (and missing values) are right-aligned, everything else left-aligned
+                if value.is_number() || value.is_null() {
+                    format!("{:>width$}", cell, width = width)
+                } else {
+                    format!("{:<width$}", cell, width = width)
+                }
+            })
+            .collect();
+        output.push_str(line.join("  ").trim_end());
+        output.push('\n');
     }
+
+    output
+}
+
+/// Render rows as CSV with a header line (RFC 4180 quoting)
+pub fn format_csv(columns: &[String], rows: &[Vec<Value>]) -> String {
+    let mut output = csv_line(columns.iter().map(|c| c.to_string()));
+    output.push_str(&format_csv_rows(rows));
+    output
+}
+
+/// Render rows as CSV lines without a header, e.g. to stream pages of results
+pub fn format_csv_rows(rows: &[Vec<Value>]) -> String {
+    rows.iter()
+        .map(|row| {
+            csv_line(row.iter().map(|value| match value {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }))
+        })
+        .collect()
+}
+
+/// One CSV line, quoting cells that contain separators, quotes or newlines
+pub fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect();
+    format!("{}\n", cells.join(","))
 }
 
 fn format_aliases(value: &Value) -> String {
@@ -265,3 +452,210 @@ fn format_datastreams(data_streams: &Value) -> String {
 
     output
 }
+
+/// Render the "profile" section of a search or ES|QL response as a tree.
+/// Bars show each node's share of its shard (or driver) time so hot branches stand out.
+pub fn format_profile(json: &str) -> String {
+    let value: Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(_) => return json.to_string(),
+    };
+    let Some(profile) = value.get("profile") else {
+        return "No profile in response\n".to_string();
+    };
+
+    let mut output = String::new();
+
+    if let Some(shards) = profile.get("shards").and_then(|s| s.as_array()) {
+        for shard in shards {
+            format_shard_profile(shard, &mut output);
+        }
+    }
+
+    // ES|QL profiles are made of drivers running operator pipelines
+    if let Some(drivers) = profile.get("drivers").and_then(|d| d.as_array()) {
+        for driver in drivers {
+            format_driver_profile(driver, &mut output);
+        }
+    }
+
+    output
+}
+
+fn format_shard_profile(shard: &Value, output: &mut String) {
+    let id = shard.get("id").and_then(|i| i.as_str()).unwrap_or("-");
+    output.push_str(&format!("=== Shard {} ===\n", id));
+
+    let searches = shard
+        .get("searches")
+        .and_then(|s| s.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let aggregations = shard
+        .get("aggregations")
+        .and_then(|a| a.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    // Total shard time, used to scale the bars
+    let total: u64 = searches
+        .iter()
+        .flat_map(|s| {
+            s.get("query")
+                .and_then(|q| q.as_array())
+                .cloned()
+                .unwrap_or_default()
+        })
+        .chain(aggregations.iter().cloned())
+        .map(|node| nanos(&node))
+        .sum::<u64>()
+        .max(1);
+
+    for search in &searches {
+        let rewrite = search
+            .get("rewrite_time")
+            .and_then(|r| r.as_u64())
+            .unwrap_or(0);
+        output.push_str(&format!("Query (rewrite {})\n", format_nanos(rewrite)));
+        if let Some(queries) = search.get("query").and_then(|q| q.as_array()) {
+            for query in queries {
+                format_profile_node(query, "type", total, 1, output);
+            }
+        }
+
+        if let Some(collectors) = search.get("collector").and_then(|c| c.as_array()) {
+            output.push_str("Collectors\n");
+            for collector in collectors {
+                format_profile_node(collector, "name", total, 1, output);
+            }
+        }
+    }
+
+    if !aggregations.is_empty() {
+        output.push_str("Aggregations\n");
+        for aggregation in &aggregations {
+            format_profile_node(aggregation, "type", total, 1, output);
+        }
+    }
+
+    if let Some(fetch) = shard.get("fetch") {
+        output.push_str(&format!("Fetch {}\n", format_nanos(nanos(fetch))));
+    }
+
+    output.push('\n');
+}
+
+/// One query/collector/aggregation node: time bar, share, duration, type and description
+fn format_profile_node(node: &Value, kind: &str, total: u64, depth: usize, output: &mut String) {
+    let prefix = "  ".repeat(depth);
+    let time = nanos(node);
+    let share = time as f64 / total as f64;
+    let name = node.get(kind).and_then(|t| t.as_str()).unwrap_or("-");
+    let description = node
+        .get("description")
+        .or_else(|| node.get("reason"))
+        .and_then(|d| d.as_str())
+        .unwrap_or("");
+
+    output.push_str(&format!(
+        "{}{:<10} {:>5.1}% {:>10}  {}  {}\n",
+        prefix,
+        "█".repeat((share * 10.0).round() as usize),
+        share * 100.0,
+        format_nanos(time),
+        name,
+        truncate(description, 80)
+    ));
+
+    // Top components of the time breakdown (the *_count entries are invocation counts)
+    if let Some(breakdown) = node.get("breakdown").and_then(|b| b.as_object()) {
+        let mut parts: Vec<(&String, u64)> = breakdown
+            .iter()
+            .filter(|(k, _)| !k.ends_with("_count"))
+            .filter_map(|(k, v)| v.as_u64().map(|n| (k, n)))
+            .filter(|(_, n)| *n > 0)
+            .collect();
+        parts.sort_by_key(|p| std::cmp::Reverse(p.1));
+        if !parts.is_empty() {
+            let parts: Vec<String> = parts
+                .iter()
+                .take(3)
+                .map(|(k, n)| format!("{} {}", k, format_nanos(*n)))
+                .collect();
+            output.push_str(&format!("{}{:<30}{}\n", prefix, "", parts.join(", ")));
+        }
+    }
+
+    if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
+        for child in children {
+            format_profile_node(child, kind, total, depth + 1, output);
+        }
+    }
+}
+
+fn format_driver_profile(driver: &Value, output: &mut String) {
+    let description = driver
+        .get("description")
+        .or_else(|| driver.get("task_description"))
+        .and_then(|d| d.as_str())
+        .unwrap_or("-");
+    let took = driver
+        .get("took_nanos")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(0);
+    let cpu = driver
+        .get("cpu_nanos")
+        .and_then(|c| c.as_u64())
+        .unwrap_or(0);
+    output.push_str(&format!(
+        "=== Driver {} (took {}, cpu {}) ===\n",
+        description,
+        format_nanos(took),
+        format_nanos(cpu)
+    ));
+
+    let total = cpu.max(1);
+    if let Some(operators) = driver.get("operators").and_then(|o| o.as_array()) {
+        for operator in operators {
+            let name = operator
+                .get("operator")
+                .and_then(|o| o.as_str())
+                .unwrap_or("-");
+            match operator
+                .get("status")
+                .and_then(|s| s.get("process_nanos"))
+                .and_then(|p| p.as_u64())
+            {
+                Some(time) => {
+                    let share = time as f64 / total as f64;
+                    output.push_str(&format!(
+                        "  {:<10} {:>5.1}% {:>10}  {}\n",
+                        "█".repeat((share * 10.0).round() as usize),
+                        share * 100.0,
+                        format_nanos(time),
+                        truncate(name, 80)
+                    ));
+                }
+                None => output.push_str(&format!("  {:<30}{}\n", "", truncate(name, 80))),
+            }
+        }
+    }
+
+    output.push('\n');
+}
+
+fn nanos(node: &Value) -> u64 {
+    node.get("time_in_nanos")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(0)
+}
+
+fn format_nanos(nanos: u64) -> String {
+    if nanos >= 1_000_000_000 {
+        format!("{:.2}s", nanos as f64 / 1e9)
+    } else if nanos >= 1_000_000 {
+        format!("{:.2}ms", nanos as f64 / 1e6)
+    } else {
+        format!("{:.1}µs", nanos as f64 / 1e3)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::client::{read_body, EsClient};
+use serde_json::{json, Value};
+
+/// Top documents per distinct value of a field
+pub struct GroupBy<'a> {
+    pub field: &'a str,
+    pub per_group: usize,
+}
+
+/// Search returning `per_group` documents for each distinct value of the field,
+/// at most `size` values (the request size). Field collapsing is used first; when
+/// Elasticsearch rejects the collapse itself (e.g., a field without doc values, or
+/// a feature collapsing doesn't support), the search is retried as a terms
+/// aggregation with top_hits. Returns the raw response body.
+pub async fn search(
+    client: &EsClient,
+    path: &str,
+    body: &Value,
+    group: &GroupBy<'_>,
+) -> Result<String, String> {
+    let response = client
+        .post(path, &collapse_body(body, group).to_string())
+        .await?;
+    let status = response.status();
+    if status != reqwest::StatusCode::BAD_REQUEST {
+        return read_body(response).await;
+    }
+
+    // Other bad requests (query syntax, unknown index...) fail the same way with
+    // the aggregation: report them as they are
+    let error = response.text().await.unwrap_or_default();
+    if !is_collapse_error(&error) {
+        return Err(format!("HTTP {}: {}", status, error));
+    }
+
+    let response = client
+        .post(path, &aggregation_body(body, group).to_string())
+        .await?;
+    read_body(response).await
+}
+
+/// Whether a 400 response is about the collapse, e.g. "cannot collapse on field
+/// `message` without `doc_values`" or "cannot use `collapse` in conjunction with ..."
+fn is_collapse_error(body: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return false;
+    };
+    let reason = |error: &Value| {
+        error
+            .get("reason")
+            .and_then(|r| r.as_str())
+            .is_some_and(|r| r.contains("collapse"))
+    };
+    let error = &value["error"];
+    reason(error)
+        || error
+            .get("root_cause")
+            .and_then(|c| c.as_array())
+            .is_some_and(|causes| causes.iter().any(reason))
+}
+
+fn collapse_body(body: &Value, group: &GroupBy) -> Value {
+    let mut body = body.clone();
+    let mut collapse = json!({ "field": group.field });
+    if group.per_group > 1 {
+        let mut inner_hits = json!({ "name": "group", "size": group.per_group });
+        if let Some(sort) = body.get("sort") {
+            inner_hits["sort"] = sort.clone();
+        }
+        if let Some(source) = body.get("_source") {
+            inner_hits["_source"] = source.clone();
+        }
+        collapse["inner_hits"] = inner_hits;
+    }
+    body["collapse"] = collapse;
+    body
+}
+
+fn aggregation_body(body: &Value, group: &GroupBy) -> Value {
+    let size = body.get("size").cloned().unwrap_or(json!(10));
+    let mut top_hits = json!({ "size": group.per_group });
+    if let Some(sort) = body.get("sort") {
+        top_hits["sort"] = sort.clone();
+    }
+    if let Some(source) = body.get("_source") {
+        top_hits["_source"] = source.clone();
+    }
+
+    let mut terms = json!({ "field": group.field, "size": size });
+    let mut aggs = json!({ "top": { "top_hits": top_hits } });
+
+    // Order the groups like collapsing does: by the sort value of their first document
+    if let Some((field, order)) = body.get("sort").and_then(first_sort) {
+        let metric = if order == "asc" { "min" } else { "max" };
+        aggs["sort_value"] = json!({ metric: { "field": field } });
+        terms["order"] = json!({ "sort_value": order });
+    }
+
+    let mut body = body.clone();
+    if let Some(body) = body.as_object_mut() {
+        body.remove("sort");
+        body.remove("_source");
+    }
+    body["size"] = json!(0);
+    body["aggs"] = json!({ "groups": { "terms": terms, "aggs": aggs } });
+    body
+}
+
+/// Field and order of the first sort clause: `[{field: order}]` or
+/// `[{field: {"order": order, ...}}]`
+fn first_sort(sort: &Value) -> Option<(String, String)> {
+    let (field, spec) = sort.as_array()?.first()?.as_object()?.iter().next()?;
+    let order = match spec {
+        Value::String(order) => order.clone(),
+        spec => spec
+            .get("order")
+            .and_then(|o| o.as_str())
+            .unwrap_or("desc")
+            .to_string(),
+    };
+    Some((field.clone(), order))
+}
+
+/// Groups of a collapsed or aggregated response: each distinct value with its documents
+pub fn parse_groups(value: &Value, field: &str) -> Vec<(Value, Vec<Value>)> {
+    if let Some(buckets) = value
+        .pointer("/aggregations/groups/buckets")
+        .and_then(|b| b.as_array())
+    {
+        return buckets
+            .iter()
+            .map(|bucket| {
+                let key = bucket
+                    .get("key_as_string")
+                    .or_else(|| bucket.get("key"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                let docs = bucket
+                    .pointer("/top/hits/hits")
+                    .and_then(|h| h.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                (key, docs)
+            })
+            .collect();
+    }
+
+    value
+        .pointer("/hits/hits")
+        .and_then(|h| h.as_array())
+        .map(|hits| {
+            hits.iter()
+                .map(|hit| {
+                    let key = hit
+                        .get("fields")
+                        .and_then(|f| f.get(field))
+                        .and_then(|v| v.get(0))
+                        .cloned()
+                        .unwrap_or(Value::Null);
+                    let docs = hit
+                        .pointer("/inner_hits/group/hits/hits")
+                        .and_then(|h| h.as_array())
+                        .cloned()
+                        .unwrap_or_else(|| vec![hit.clone()]);
+                    (key, docs)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
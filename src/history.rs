@@ -0,0 +1,101 @@
+use crate::config::config_dir;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// One recorded command invocation
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String,
+    /// Arguments without the binary name
+    pub args: Vec<String>,
+    /// Cluster the command ran against (ELASTICSEARCH_URL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// Server-side time in milliseconds, summed over all requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub took: Option<u64>,
+    /// Total hits (or count) of the last response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hits: Option<u64>,
+    pub exit_status: i32,
+}
+
+struct ResponseStats {
+    took: Option<u64>,
+    hits: Option<u64>,
+}
+
+static STATS: Mutex<ResponseStats> = Mutex::new(ResponseStats {
+    took: None,
+    hits: None,
+});
+
+/// Remember timing and hit count of a response body for the history entry
+pub fn observe(body: &str) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return;
+    };
+    let mut stats = STATS.lock().unwrap();
+
+    if let Some(took) = value.get("took").and_then(|t| t.as_u64()) {
+        stats.took = Some(stats.took.unwrap_or(0) + took);
+    }
+
+    let hits = value
+        .get("hits")
+        .and_then(|h| h.get("total"))
+        .and_then(|t| t.get("value").or(Some(t)))
+        .and_then(|v| v.as_u64())
+        .or_else(|| value.get("count").and_then(|c| c.as_u64()));
+    if hits.is_some() {
+        stats.hits = hits;
+    }
+}
+
+fn path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("history.jsonl"))
+}
+
+/// Append an invocation to the history file
+pub fn record(args: &[String], exit_status: i32) -> Result<(), String> {
+    let stats = STATS.lock().unwrap();
+    let entry = Entry {
+        timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        args: args.to_vec(),
+        cluster: std::env::var("ELASTICSEARCH_URL").ok(),
+        took: stats.took,
+        hits: stats.hits,
+        exit_status,
+    };
+
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+    }
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Load all history entries with their 1-based number, oldest first.
+/// Unreadable lines are skipped but keep their number.
+pub fn load() -> Result<Vec<(usize, Entry)>, String> {
+    let path = path()?;
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| serde_json::from_str(line).ok().map(|e| (i + 1, e)))
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+    }
+}
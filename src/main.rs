@@ -1,13 +1,21 @@
 mod client;
 mod commands;
+mod config;
 mod format;
+mod group;
+mod history;
+mod query;
+mod timerange;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use commands::async_query::AsyncKind;
+use timerange::TimeArgs;
 
 #[derive(Parser)]
 #[command(
     name = "es-cli",
     version,
+    args_override_self = true,
     about = "Minimal CLI for Elasticsearch",
     long_about = "A minimal CLI to interact with Elasticsearch.\n\n\
                   Requires environment variables:\n  \
@@ -27,20 +35,98 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Build nested aggregations and show one row per bucket
+    #[command(
+        after_help = "Example:\n  es-cli agg logs --by service.name --by date_histogram:@timestamp:1h --metric p95:duration"
+    )]
+    Agg {
+        /// Index name or pattern
+        index: String,
+
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error")
+        query: Option<String>,
+
+        /// Bucket level, outermost first (repeatable): field, terms:field[:size],
+        /// date_histogram:field:interval or histogram:field:interval
+        #[arg(short = 'b', long)]
+        by: Vec<String>,
+
+        /// Metric computed per bucket (repeatable): avg|sum|min|max|count|cardinality|pNN:field
+        #[arg(short = 'm', long)]
+        metric: Vec<String>,
+
+        /// CSV output
+        #[arg(long)]
+        csv: bool,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Timestamp field name for time filters (default: @timestamp)
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
+    },
+
     /// List index aliases (GET /_alias)
     Aliases {
         /// Optional pattern to filter aliases (supports wildcards, e.g., "*audit*")
         pattern: Option<String>,
     },
 
+    /// Follow up on queries started with --async
+    Async {
+        #[command(subcommand)]
+        action: AsyncAction,
+    },
+
+    /// Compare counts, top values and histograms of a time window with an earlier one
+    Compare {
+        /// Index name or pattern
+        index: String,
+
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error AND host:prod-*")
+        query: Option<String>,
+
+        /// Length of the compared windows, the current one ending now (e.g., 15m, 1h, 1d)
+        #[arg(short, long, default_value = "1h")]
+        window: String,
+
+        /// How far back the previous window is (e.g., 1d for the same time yesterday, 1w)
+        #[arg(long, default_value = "1d")]
+        vs: String,
+
+        /// Field to compare top values of (can be repeated)
+        #[arg(short = 'f', long = "field")]
+        fields: Vec<String>,
+
+        /// Number of top values per field and window
+        #[arg(short = 'n', long, default_value = "10")]
+        size: usize,
+
+        /// Number of histogram buckets per window
+        #[arg(short = 'b', long, default_value = "12")]
+        buckets: usize,
+
+        /// Timestamp field name for the windows (default: @timestamp)
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
+    },
+
     /// Count documents in an index (GET /<index>/_count)
     Count {
         /// Index name or pattern
         index: String,
 
-        /// Optional query DSL as JSON (e.g., '{"query":{"match":{"status":"error"}}}')
-        #[arg(value_name = "JSON")]
+        /// Optional query as KQL/Lucene or JSON DSL
+        /// Example: "status:error" or '{"query":{"match":{"status":"error"}}}'
         query: Option<String>,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Timestamp field name for time filters (default: @timestamp)
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
     },
 
     /// List datastreams (GET /_data_stream)
@@ -54,6 +140,19 @@ enum Commands {
     Fields {
         /// Index name or pattern
         index: String,
+
+        /// Use _field_caps: flag fields mapped with different types across indices,
+        /// and show whether fields are searchable and aggregatable
+        #[arg(long)]
+        caps: bool,
+
+        /// Show the percentage of documents containing each field
+        #[arg(long)]
+        coverage: bool,
+
+        /// Number of documents sampled for --coverage on large indices
+        #[arg(long, default_value = "10000", requires = "coverage", value_parser = clap::value_parser!(u64).range(1..))]
+        sample_size: u64,
     },
 
     /// Show document counts over time (date histogram)
@@ -61,26 +160,98 @@ enum Commands {
         /// Index name or pattern
         index: String,
 
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error AND host:prod-*")
+        query: Option<String>,
+
         /// Date field to aggregate (default: @timestamp)
         #[arg(short = 'f', long, default_value = "@timestamp")]
         field: String,
 
-        /// Time interval (e.g., "1h", "1d", "5m")
-        #[arg(short = 'i', long, default_value = "1h")]
-        interval: String,
+        /// Time interval, fixed or calendar (e.g., "5m", "1h", "1d", "1w", "1M") (default: 1h)
+        #[arg(short = 'i', long)]
+        interval: Option<String>,
+
+        /// Pick the interval to get about this many buckets over the time range
+        #[arg(short = 'b', long, conflicts_with = "interval")]
+        buckets: Option<usize>,
+
+        /// Break down each bucket by the top terms of this field
+        #[arg(short = 's', long)]
+        split_by: Option<String>,
+
+        /// Number of top terms to show with --split-by
+        #[arg(long, default_value = "5", requires = "split_by")]
+        split_size: usize,
+
+        #[command(flatten)]
+        time: TimeArgs,
+    },
+
+    /// Show recorded command invocations, most recent last
+    #[command(args_conflicts_with_subcommands = true)]
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+
+        /// Only show entries whose command contains this text
+        search: Option<String>,
+
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        size: usize,
     },
 
     /// List all indices (GET /_cat/indices?format=json)
     #[command(alias = "indices")]
     List,
 
+    /// Run a batch of searches from a file (POST /_msearch)
+    #[command(
+        after_help = "The file is _msearch NDJSON (header/body line pairs), or a YAML list of\n\
+                      label/index/query/size entries when it ends in .yaml or .yml. Use - for stdin."
+    )]
+    Msearch {
+        /// NDJSON or YAML file with the searches
+        file: String,
+
+        /// Number of searches sent per _msearch request
+        #[arg(short = 'b', long, default_value = "20")]
+        batch_size: usize,
+    },
+
     /// Get mapping for an index (GET /<index>/_mapping)
-    #[command(alias = "mapping")]
     Get {
         /// Index name or pattern (e.g., "my-index" or "logs-*")
         index: String,
     },
 
+    /// Show or compare index mappings (`mapping <index>` is the same as `get`)
+    #[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+    Mapping {
+        #[command(subcommand)]
+        action: Option<MappingAction>,
+
+        /// Index name or pattern to get the mapping of
+        index: Option<String>,
+    },
+
+    /// Summarize an index: size, shards, health, aliases, ILM phase, field count
+    /// and time span
+    Describe {
+        /// Index, alias or data stream
+        index: String,
+
+        /// Field for the oldest and newest document times
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
+    },
+
+    /// Print documents by ID (GET /<index>/_doc/<id>, POST /_mget)
+    Doc {
+        #[command(subcommand)]
+        action: DocAction,
+    },
+
     /// Search an index with a JSON query body (POST /<index>/_search)
     Search {
         /// Index name or pattern to search
@@ -89,6 +260,14 @@ enum Commands {
         /// Elasticsearch query DSL as JSON string
         #[arg(value_name = "JSON")]
         query: String,
+
+        /// Profile the query and show a timing tree (with -H)
+        #[arg(long)]
+        profile: bool,
+
+        /// Run as an async query, polling until it completes (Ctrl-C prints its ID)
+        #[arg(long = "async")]
+        run_async: bool,
     },
 
     /// Execute an ES|QL query (POST /_query)
@@ -96,6 +275,107 @@ enum Commands {
     Esql {
         /// ES|QL query string (e.g., "FROM logs | LIMIT 10")
         query: String,
+
+        /// Value for a ?name placeholder, as name=value (can be repeated)
+        /// Example: -p status=500 -p host=prod-1
+        #[arg(short = 'p', long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+
+        /// Query DSL or KQL/Lucene filter applied before the ES|QL query
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Return values column by column instead of row by row
+        #[arg(long)]
+        columnar: bool,
+
+        /// Locale for formatting dates and numbers (e.g., fr-FR)
+        #[arg(long)]
+        locale: Option<String>,
+
+        /// Timezone for dates (e.g., Europe/Paris, +02:00)
+        #[arg(long)]
+        time_zone: Option<String>,
+
+        /// Let the server format the response and print it as is
+        #[arg(long, value_parser = ["csv", "tsv", "txt", "arrow"], conflicts_with_all = ["profile", "columnar", "run_async"])]
+        format: Option<String>,
+
+        /// Profile the query and show a timing tree (with -H)
+        #[arg(long)]
+        profile: bool,
+
+        /// Run as an async query, polling until it completes (Ctrl-C prints its ID)
+        #[arg(long = "async")]
+        run_async: bool,
+    },
+
+    /// Run an EQL search for events or sequences (POST /<index>/_eql/search)
+    Eql {
+        /// Index name or pattern
+        index: String,
+
+        /// EQL query (e.g., "process where process.name == \"cmd.exe\"")
+        query: String,
+
+        /// Field holding the event time
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
+
+        /// Field used to order events with the same timestamp
+        #[arg(long)]
+        tiebreaker: Option<String>,
+
+        /// Number of events or sequences to return
+        #[arg(short = 'n', long, default_value = "10")]
+        size: usize,
+
+        /// Run as an async search, polling until it completes (Ctrl-C prints its ID)
+        #[arg(long = "async")]
+        run_async: bool,
+    },
+
+    /// Execute an SQL query, fetching all pages (POST /_sql)
+    Sql {
+        /// SQL query (e.g., "SELECT host, COUNT(*) FROM logs GROUP BY host")
+        query: String,
+
+        /// Value for a ? placeholder, in order (can be repeated)
+        /// Example: -p 500 -p '"prod-1"'
+        #[arg(short = 'p', long = "param", value_name = "VALUE")]
+        params: Vec<String>,
+
+        /// Query DSL or KQL/Lucene filter applied before the SQL query
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Timezone for dates (e.g., Europe/Paris, +02:00)
+        #[arg(long)]
+        time_zone: Option<String>,
+
+        /// Number of rows fetched per page
+        #[arg(long, default_value = "1000")]
+        fetch_size: usize,
+
+        /// Let the server format the response and print it as is
+        #[arg(long, value_parser = ["csv", "tsv", "txt"], conflicts_with = "translate")]
+        format: Option<String>,
+
+        /// Show the Query DSL the SQL query translates to, without running it
+        #[arg(long)]
+        translate: bool,
+    },
+
+    /// Explain why a document matches a query or not (POST /<index>/_explain/<id>)
+    Explain {
+        /// Index name
+        index: String,
+
+        /// Document ID
+        id: String,
+
+        /// Query as JSON DSL or KQL/Lucene (e.g., "status:error")
+        query: String,
     },
 
     /// Search with KQL/Lucene query string syntax
@@ -121,30 +401,61 @@ enum Commands {
         #[arg(short = 'f', long)]
         fields: Option<String>,
 
-        /// Time filter: documents from last duration (e.g., "1h", "30m", "7d")
-        #[arg(long)]
-        since: Option<String>,
+        /// Return the top documents for each distinct value of this field
+        /// (-n then limits the number of values)
+        #[arg(short = 'g', long)]
+        group_by: Option<String>,
 
-        /// Time filter: start time (RFC3339 or Elasticsearch format)
-        #[arg(long)]
-        from: Option<String>,
+        /// Number of documents per value with --group-by
+        #[arg(long, default_value = "1", requires = "group_by")]
+        per_group: usize,
 
-        /// Time filter: end time (RFC3339 or Elasticsearch format)
-        #[arg(long)]
-        to: Option<String>,
+        #[command(flatten)]
+        time: TimeArgs,
 
         /// Timestamp field name for time filters (default: @timestamp)
         #[arg(long, default_value = "@timestamp")]
         timestamp_field: String,
+
+        /// Profile the query and show a timing tree (with -H)
+        #[arg(long)]
+        profile: bool,
+
+        /// Run as an async search, polling until it completes (Ctrl-C prints its ID)
+        #[arg(long = "async", conflicts_with = "group_by")]
+        run_async: bool,
     },
 
-    /// Show statistics for a numeric field (min, max, avg, sum, std_dev)
+    /// Interactive ES|QL shell with history and completion
+    Repl,
+
+    /// Manage saved command invocations (stored in the config file)
+    Saved {
+        #[command(subcommand)]
+        action: SavedAction,
+    },
+
+    /// Show statistics for numeric fields (min, max, avg, sum, std_dev, distinct, percentiles)
     Stats {
         /// Index name or pattern
         index: String,
 
-        /// Numeric field name
+        /// Numeric field name, or comma-separated field names (e.g., duration,bytes)
         field: String,
+
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error AND host:prod-*")
+        query: Option<String>,
+
+        /// Comma-separated percentiles to compute (e.g., 50,90,99)
+        #[arg(short, long)]
+        percentiles: Option<String>,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Timestamp field name for time filters (default: @timestamp)
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
     },
 
     /// Show most recent documents from an index (sorted by @timestamp)
@@ -155,6 +466,55 @@ enum Commands {
         /// Number of documents to show
         #[arg(short = 'n', long, default_value = "10")]
         size: usize,
+
+        /// Return the top documents for each distinct value of this field
+        /// (-n then limits the number of values)
+        #[arg(short = 'g', long)]
+        group_by: Option<String>,
+
+        /// Number of documents per value with --group-by
+        #[arg(long, default_value = "1", requires = "group_by")]
+        per_group: usize,
+    },
+
+    /// Show random documents, for a representative look at an index
+    Sample {
+        /// Index name or pattern
+        index: String,
+
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error")
+        query: Option<String>,
+
+        /// Number of documents to show
+        #[arg(short = 'n', long, default_value = "20")]
+        size: usize,
+
+        /// Seed for a reproducible sample (a 32-bit integer)
+        #[arg(long)]
+        seed: Option<i32>,
+
+        /// Use the random_sampler aggregation (faster on large indices)
+        #[arg(long)]
+        sampler: bool,
+    },
+
+    /// Manage and run stored templates
+    Template {
+        #[command(subcommand)]
+        kind: TemplateKind,
+    },
+
+    /// Check that a query is valid and show its Lucene rewrite (POST /<index>/_validate/query)
+    Validate {
+        /// Index name or pattern
+        index: String,
+
+        /// Query as JSON DSL or KQL/Lucene (e.g., "status:error AND host:prod-*")
+        query: String,
+
+        /// Run the validation on every shard instead of a random one
+        #[arg(long)]
+        all_shards: bool,
     },
 
     /// Show top unique values for a field (terms aggregation)
@@ -163,49 +523,613 @@ enum Commands {
         /// Index name or pattern
         index: String,
 
-        /// Field name to aggregate
+        /// Field name to aggregate (comma-separated fields with --all)
         field: String,
 
+        /// Optional query as KQL/Lucene or JSON DSL (e.g., "status:error AND host:prod-*")
+        query: Option<String>,
+
         /// Number of top values to show
         #[arg(short = 'n', long, default_value = "10")]
         size: usize,
+
+        /// Also count documents without the field
+        #[arg(long)]
+        missing: bool,
+
+        /// Only show values matching this regex (e.g., "prod-.*")
+        #[arg(long)]
+        include: Option<String>,
+
+        /// Hide values matching this regex
+        #[arg(long)]
+        exclude: Option<String>,
+
+        /// Bucket order: count, key, asc or desc (count:asc, key:desc...)
+        #[arg(long, conflicts_with_all = ["significant", "rare"])]
+        order: Option<String>,
+
+        /// Only show values with at least this many documents
+        #[arg(long, conflicts_with = "rare")]
+        min_count: Option<u64>,
+
+        /// Show values unusually frequent in matching documents compared to the
+        /// background (significant_terms)
+        #[arg(long, conflicts_with = "rare")]
+        significant: bool,
+
+        /// Background query for --significant (default: all documents in the time range)
+        #[arg(long, requires = "significant")]
+        background: Option<String>,
+
+        /// Show the least frequent values (rare_terms)
+        #[arg(long)]
+        rare: bool,
+
+        /// Maximum document count of a value for --rare
+        #[arg(long, default_value = "1", requires = "rare")]
+        max_count: u64,
+
+        /// List every distinct value with a paged composite aggregation, streaming
+        /// rows (JSON lines by default); several comma-separated fields give tuples
+        #[arg(
+            long,
+            conflicts_with_all = ["include", "exclude", "order", "min_count", "significant", "rare"]
+        )]
+        all: bool,
+
+        /// CSV output for --all
+        #[arg(long, requires = "all")]
+        csv: bool,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Timestamp field name for time filters (default: @timestamp)
+        #[arg(long, default_value = "@timestamp")]
+        timestamp_field: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MappingAction {
+    /// Get mapping for an index, same as `get` (GET /<index>/_mapping)
+    Get {
+        /// Index name or pattern (e.g., "my-index" or "logs-*")
+        index: String,
+    },
+
+    /// Compare two mappings: added, removed and changed fields
+    #[command(
+        after_help = "Exits with status 2 when the mappings differ, 1 on errors.\n\n\
+                      Example:\n  es-cli mapping diff logs-2024.01 logs-2024.02"
+    )]
+    Diff {
+        /// Index with the old mapping (the new one with --baseline)
+        index: String,
+
+        /// Index with the new mapping
+        #[arg(required_unless_present = "baseline", conflicts_with = "baseline")]
+        other: Option<String>,
+
+        /// JSON file with the expected mapping (GET _mapping output, an index
+        /// template or a mappings object)
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DocAction {
+    /// Print one document
+    Get {
+        /// Index name
+        index: String,
+
+        /// Document ID
+        id: String,
+
+        #[command(flatten)]
+        source: DocArgs,
+    },
+
+    /// Print the documents listed on stdin, one index/id per line
+    #[command(after_help = "Example:\n  printf 'logs/1\\nlogs/2\\n' | es-cli doc mget -H")]
+    Mget {
+        #[command(flatten)]
+        source: DocArgs,
+    },
+}
+
+#[derive(Args)]
+struct DocArgs {
+    /// Only return these source fields (comma-separated, wildcards allowed)
+    #[arg(long, value_name = "FIELDS")]
+    include: Option<String>,
+
+    /// Leave these source fields out (comma-separated, wildcards allowed)
+    #[arg(long, value_name = "FIELDS")]
+    exclude: Option<String>,
+
+    /// Routing value the documents were indexed with
+    #[arg(long)]
+    routing: Option<String>,
+
+    /// Print only the document source, without metadata
+    #[arg(long)]
+    raw: bool,
+}
+
+impl DocArgs {
+    fn options(&self) -> commands::doc::DocOptions<'_> {
+        commands::doc::DocOptions {
+            includes: self.include.as_deref(),
+            excludes: self.exclude.as_deref(),
+            routing: self.routing.as_deref(),
+            raw: self.raw,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum AsyncAction {
+    /// Show the results, waiting for the query if it is still running
+    Get {
+        /// Async query ID
+        id: String,
+
+        /// The ID is an async ES|QL query (default: async search)
+        #[arg(long, conflicts_with = "eql")]
+        esql: bool,
+
+        /// The ID is an EQL search (default: async search)
+        #[arg(long)]
+        eql: bool,
+    },
+
+    /// Show whether the query is still running
+    Status {
+        /// Async query ID
+        id: String,
+
+        /// The ID is an async ES|QL query (default: async search)
+        #[arg(long, conflicts_with = "eql")]
+        esql: bool,
+
+        /// The ID is an EQL search (default: async search)
+        #[arg(long)]
+        eql: bool,
+    },
+
+    /// Delete the query and its results, cancelling it if it is still running
+    #[command(alias = "rm")]
+    Delete {
+        /// Async query ID
+        id: String,
+
+        /// The ID is an async ES|QL query (default: async search)
+        #[arg(long, conflicts_with = "eql")]
+        esql: bool,
+
+        /// The ID is an EQL search (default: async search)
+        #[arg(long)]
+        eql: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SavedAction {
+    /// Save a command invocation under a name
+    #[command(
+        after_help = "Example:\n  es-cli saved add api-errors kql logs 'status:error' --since 1h -H"
+    )]
+    Add {
+        /// Name of the saved invocation
+        name: String,
+
+        /// Command and arguments to save
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// List saved invocations
+    List,
+
+    /// Run a saved invocation; extra arguments override the saved ones
+    #[command(after_help = "Example:\n  es-cli saved run api-errors --since 15m")]
+    Run {
+        /// Name of the saved invocation
+        name: String,
+
+        /// Arguments appended to the saved command (e.g., --since 15m)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        overrides: Vec<String>,
+    },
+
+    /// Remove a saved invocation
+    #[command(alias = "remove")]
+    Rm {
+        /// Name of the saved invocation
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateKind {
+    /// Mustache search templates (/_scripts, /_search/template)
+    Search {
+        #[command(subcommand)]
+        action: SearchTemplateAction,
     },
 }
 
+#[derive(Subcommand)]
+enum SearchTemplateAction {
+    /// List stored search templates (requires the monitor cluster privilege)
+    List,
+
+    /// Show a stored template (GET /_scripts/<id>)
+    Get {
+        /// Template ID
+        id: String,
+    },
+
+    /// Store a template (PUT /_scripts/<id>)
+    Put {
+        /// Template ID
+        id: String,
+
+        /// Template source as JSON or mustache text, or @file to read it from a file
+        /// Example: '{"query":{"match":{"service":"{{service}}"}},"size":"{{size}}"}'
+        source: String,
+    },
+
+    /// Delete a stored template (DELETE /_scripts/<id>)
+    #[command(alias = "rm")]
+    Delete {
+        /// Template ID
+        id: String,
+    },
+
+    /// Show the query a template renders to (POST /_render/template)
+    Render {
+        /// Template ID
+        id: String,
+
+        /// Template parameter (repeatable); JSON values keep their type
+        /// Example: --param service=api --param size=20
+        #[arg(short = 'p', long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+    },
+
+    /// Search an index with a stored template (POST /<index>/_search/template)
+    Run {
+        /// Index name or pattern
+        index: String,
+
+        /// Template ID
+        id: String,
+
+        /// Template parameter (repeatable); JSON values keep their type
+        /// Example: --param service=api --param size=20
+        #[arg(short = 'p', long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Run a recorded invocation again; extra arguments override the recorded ones
+    #[command(after_help = "Example:\n  es-cli history rerun 42 --since 15m")]
+    Rerun {
+        /// Entry number as shown by `history`
+        n: usize,
+
+        /// Arguments appended to the recorded command (e.g., --since 15m)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        overrides: Vec<String>,
+    },
+}
+
+/// Parse a command invocation given without the binary name
+fn parse_args(args: &[String]) -> Result<Cli, String> {
+    let argv = std::iter::once("es-cli".to_string()).chain(args.iter().cloned());
+    Cli::try_parse_from(argv).map_err(|e| {
+        let message = e.to_string();
+        message.trim_start_matches("error: ").trim_end().to_string()
+    })
+}
+
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut cli = Cli::parse();
+
+    // Replace saved invocations and history replays by the command they stand for
+    let expanded = match &cli.command {
+        Commands::Saved {
+            action: SavedAction::Run { name, overrides },
+        } => Some(commands::saved::expand(
+            &Cli::command(),
+            name,
+            overrides,
+            cli.human,
+        )),
+        Commands::History {
+            action: Some(HistoryAction::Rerun { n, overrides }),
+            ..
+        } => Some(commands::history::expand(
+            &Cli::command(),
+            *n,
+            overrides,
+            cli.human,
+        )),
+        _ => None,
+    };
+    if let Some(expanded) = expanded {
+        match expanded.and_then(|a| parse_args(&a).map(|c| (a, c))) {
+            Ok((expanded_args, expanded_cli)) => {
+                args = expanded_args;
+                cli = expanded_cli;
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Only commands that talk to the cluster are worth recording
+    let record = !matches!(
+        cli.command,
+        Commands::History { .. } | Commands::Repl | Commands::Saved { .. }
+    );
+
+    // Exit status of commands that succeed with a non-zero status
+    let mut status = 0;
 
     let result = match cli.command {
+        Commands::Agg {
+            index,
+            query,
+            by,
+            metric,
+            csv,
+            time,
+            timestamp_field,
+        } => {
+            let opts = commands::agg::AggOptions {
+                index: &index,
+                query: query.as_deref(),
+                by: &by,
+                metrics: &metric,
+                time: &time,
+                timestamp_field: &timestamp_field,
+                csv,
+            };
+            commands::agg::run(opts, cli.human).await
+        }
         Commands::Aliases { pattern } => {
             commands::aliases::run(pattern.as_deref(), cli.human).await
         }
-        Commands::Count { index, query } => {
-            commands::count::run(&index, query.as_deref(), cli.human).await
+        Commands::Async { action } => match action {
+            AsyncAction::Get { id, esql, eql } => {
+                let kind = AsyncKind::from_flags(esql, eql);
+                commands::async_query::get(&id, kind, cli.human).await
+            }
+            AsyncAction::Status { id, esql, eql } => {
+                let kind = AsyncKind::from_flags(esql, eql);
+                commands::async_query::status(&id, kind, cli.human).await
+            }
+            AsyncAction::Delete { id, esql, eql } => {
+                let kind = AsyncKind::from_flags(esql, eql);
+                commands::async_query::delete(&id, kind, cli.human).await
+            }
+        },
+        Commands::Compare {
+            index,
+            query,
+            window,
+            vs,
+            fields,
+            size,
+            buckets,
+            timestamp_field,
+        } => {
+            let opts = commands::compare::CompareOptions {
+                index: &index,
+                query: query.as_deref(),
+                window: &window,
+                vs: &vs,
+                fields: &fields,
+                size,
+                buckets,
+                timestamp_field: &timestamp_field,
+            };
+            commands::compare::run(opts, cli.human).await
+        }
+        Commands::Count {
+            index,
+            query,
+            time,
+            timestamp_field,
+        } => {
+            commands::count::run(&index, query.as_deref(), &time, &timestamp_field, cli.human).await
         }
         Commands::Datastreams { pattern } => {
             commands::datastreams::run(pattern.as_deref(), cli.human).await
         }
-        Commands::Fields { index } => commands::fields::run(&index, cli.human).await,
+        Commands::Fields {
+            index,
+            caps,
+            coverage,
+            sample_size,
+        } => {
+            let opts = commands::fields::FieldsOptions {
+                index: &index,
+                caps,
+                coverage,
+                sample_size,
+            };
+            commands::fields::run(opts, cli.human).await
+        }
         Commands::Histogram {
             index,
+            query,
             field,
             interval,
-        } => commands::histogram::run(&index, &field, &interval, cli.human).await,
+            buckets,
+            split_by,
+            split_size,
+            time,
+        } => {
+            let opts = commands::histogram::HistogramOptions {
+                index: &index,
+                query: query.as_deref(),
+                field: &field,
+                interval: interval.as_deref(),
+                buckets,
+                split_by: split_by.as_deref(),
+                split_size,
+                time: &time,
+            };
+            commands::histogram::run(opts, cli.human).await
+        }
+        Commands::History {
+            action,
+            search,
+            size,
+        } => match action {
+            Some(HistoryAction::Rerun { .. }) => {
+                Err("A recorded invocation cannot be a 'history' command".to_string())
+            }
+            None => commands::history::run(search.as_deref(), size, cli.human),
+        },
         Commands::List => commands::list::run(cli.human).await,
+        Commands::Msearch { file, batch_size } => {
+            commands::msearch::run(&file, batch_size, cli.human).await
+        }
         Commands::Get { index } => commands::get::run(&index, cli.human).await,
-        Commands::Search { index, query } => commands::search::run(&index, &query, cli.human).await,
-        Commands::Esql { query } => commands::esql::run(&query, cli.human).await,
+        Commands::Mapping { action, index } => match action {
+            Some(MappingAction::Get { index }) => commands::get::run(&index, cli.human).await,
+            Some(MappingAction::Diff {
+                index,
+                other,
+                baseline,
+            }) => {
+                let diff = commands::mapping::diff(
+                    &index,
+                    other.as_deref(),
+                    baseline.as_deref(),
+                    cli.human,
+                );
+                match diff.await {
+                    Ok(true) => {
+                        status = commands::mapping::DIFFERENCES_STATUS;
+                        Ok(())
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+            None => match index {
+                Some(index) => commands::get::run(&index, cli.human).await,
+                None => Err("Missing index (see es-cli mapping --help)".to_string()),
+            },
+        },
+        Commands::Describe {
+            index,
+            timestamp_field,
+        } => commands::describe::run(&index, &timestamp_field, cli.human).await,
+        Commands::Doc { action } => match action {
+            DocAction::Get { index, id, source } => {
+                commands::doc::get(&index, &id, source.options(), cli.human).await
+            }
+            DocAction::Mget { source } => commands::doc::mget(source.options(), cli.human).await,
+        },
+        Commands::Search {
+            index,
+            query,
+            profile,
+            run_async,
+        } => commands::search::run(&index, &query, profile, run_async, cli.human).await,
+        Commands::Esql {
+            query,
+            params,
+            filter,
+            columnar,
+            locale,
+            time_zone,
+            format,
+            profile,
+            run_async,
+        } => {
+            let opts = commands::esql::EsqlOptions {
+                query: &query,
+                params: &params,
+                filter: filter.as_deref(),
+                columnar,
+                locale: locale.as_deref(),
+                time_zone: time_zone.as_deref(),
+                format: format.as_deref(),
+                profile,
+                run_async,
+            };
+            commands::esql::run(opts, cli.human).await
+        }
+        Commands::Eql {
+            index,
+            query,
+            timestamp_field,
+            tiebreaker,
+            size,
+            run_async,
+        } => {
+            let opts = commands::eql::EqlOptions {
+                index: &index,
+                query: &query,
+                timestamp_field: &timestamp_field,
+                tiebreaker: tiebreaker.as_deref(),
+                size,
+                run_async,
+            };
+            commands::eql::run(opts, cli.human).await
+        }
+        Commands::Sql {
+            query,
+            params,
+            filter,
+            time_zone,
+            fetch_size,
+            format,
+            translate,
+        } => {
+            let opts = commands::sql::SqlOptions {
+                query: &query,
+                params: &params,
+                filter: filter.as_deref(),
+                time_zone: time_zone.as_deref(),
+                fetch_size,
+                format: format.as_deref(),
+                translate,
+            };
+            commands::sql::run(opts, cli.human).await
+        }
+        Commands::Explain { index, id, query } => {
+            commands::explain::run(&index, &id, &query, cli.human).await
+        }
         Commands::Kql {
             index,
             query,
             size,
             sort,
             fields,
-            since,
-            from,
-            to,
+            group_by,
+            per_group,
+            time,
             timestamp_field,
+            profile,
+            run_async,
         } => {
             let opts = commands::kql::KqlOptions {
                 index: &index,
@@ -213,22 +1137,145 @@ async fn main() {
                 size,
                 sort: sort.as_deref(),
                 fields: fields.as_deref(),
-                since: since.as_deref(),
-                from: from.as_deref(),
-                to: to.as_deref(),
+                group_by: group_by.as_deref(),
+                per_group,
+                time: &time,
                 timestamp_field: &timestamp_field,
+                profile,
+                run_async,
             };
             commands::kql::run(opts, cli.human).await
         }
-        Commands::Stats { index, field } => commands::stats::run(&index, &field, cli.human).await,
-        Commands::Tail { index, size } => commands::tail::run(&index, size, cli.human).await,
-        Commands::Values { index, field, size } => {
-            commands::values::run(&index, &field, size, cli.human).await
+        Commands::Repl => commands::repl::run().await,
+        Commands::Saved { action } => match action {
+            SavedAction::Add { name, args } => match parse_args(&args) {
+                Ok(Cli {
+                    command: Commands::Saved { .. },
+                    ..
+                }) => Err("A saved invocation cannot be a 'saved' command".to_string()),
+                Ok(_) => commands::saved::add(&name, &args),
+                Err(e) => Err(e),
+            },
+            SavedAction::List => commands::saved::list(cli.human),
+            SavedAction::Run { .. } => {
+                Err("A saved invocation cannot be a 'saved' command".to_string())
+            }
+            SavedAction::Rm { name } => commands::saved::remove(&name),
+        },
+        Commands::Stats {
+            index,
+            field,
+            query,
+            percentiles,
+            time,
+            timestamp_field,
+        } => {
+            commands::stats::run(
+                &index,
+                &field,
+                query.as_deref(),
+                &time,
+                &timestamp_field,
+                percentiles.as_deref(),
+                cli.human,
+            )
+            .await
+        }
+        Commands::Tail {
+            index,
+            size,
+            group_by,
+            per_group,
+        } => commands::tail::run(&index, size, group_by.as_deref(), per_group, cli.human).await,
+        Commands::Sample {
+            index,
+            query,
+            size,
+            seed,
+            sampler,
+        } => {
+            let opts = commands::sample::SampleOptions {
+                index: &index,
+                query: query.as_deref(),
+                size,
+                seed,
+                sampler,
+            };
+            commands::sample::run(opts, cli.human).await
+        }
+        Commands::Template {
+            kind: TemplateKind::Search { action },
+        } => match action {
+            SearchTemplateAction::List => commands::template::list(cli.human).await,
+            SearchTemplateAction::Get { id } => commands::template::get(&id, cli.human).await,
+            SearchTemplateAction::Put { id, source } => {
+                commands::template::put(&id, &source, cli.human).await
+            }
+            SearchTemplateAction::Delete { id } => commands::template::delete(&id, cli.human).await,
+            SearchTemplateAction::Render { id, params } => {
+                commands::template::render(&id, &params, cli.human).await
+            }
+            SearchTemplateAction::Run { index, id, params } => {
+                commands::template::run(&index, &id, &params, cli.human).await
+            }
+        },
+        Commands::Validate {
+            index,
+            query,
+            all_shards,
+        } => commands::validate::run(&index, &query, all_shards, cli.human).await,
+        Commands::Values {
+            index,
+            field,
+            query,
+            size,
+            missing,
+            include,
+            exclude,
+            order,
+            min_count,
+            significant,
+            background,
+            rare,
+            max_count,
+            all,
+            csv,
+            time,
+            timestamp_field,
+        } => {
+            let opts = commands::values::ValuesOptions {
+                index: &index,
+                field: &field,
+                query: query.as_deref(),
+                size,
+                missing,
+                include: include.as_deref(),
+                exclude: exclude.as_deref(),
+                order: order.as_deref(),
+                min_count,
+                significant,
+                background: background.as_deref(),
+                rare,
+                max_count,
+                all,
+                csv,
+                time: &time,
+                timestamp_field: &timestamp_field,
+            };
+            commands::values::run(opts, cli.human).await
         }
     };
 
+    if record {
+        // History is best effort and must never fail the command itself
+        let _ = history::record(&args, if result.is_ok() { status } else { 1 });
+    }
+
     if let Err(e) = result {
         eprintln!("Error: {e}");
         std::process::exit(1);
     }
+    if status != 0 {
+        std::process::exit(status);
+    }
 }
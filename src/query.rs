@@ -0,0 +1,64 @@
+use serde_json::{json, Map, Value};
+
+/// Build a query_string clause for KQL/Lucene syntax.
+///
+/// query_string respects field mappings better than simple_query_string
+/// (e.g. keyword fields, wildcards, NOT operator). lenient=true prevents errors
+/// on type mismatches, analyze_wildcard enables wildcard expansion on analyzed fields.
+pub fn query_string(query: &str) -> Value {
+    json!({
+        "query_string": {
+            "query": query,
+            "default_operator": "AND",
+            "lenient": true,
+            "analyze_wildcard": true
+        }
+    })
+}
+
+/// Parse a user query given either as JSON DSL or as KQL/Lucene.
+///
+/// JSON input may be a full request body ({"query": {...}}) or a bare query clause.
+pub fn parse_query(input: &str) -> Result<Value, String> {
+    if !input.trim_start().starts_with('{') {
+        return Ok(query_string(input));
+    }
+
+    let value: Value =
+        serde_json::from_str(input).map_err(|e| format!("Invalid JSON query: {e}"))?;
+    match value.get("query") {
+        Some(query) => Ok(query.clone()),
+        None => Ok(value),
+    }
+}
+
+/// Parse an optional user query, matching all documents when none is given
+pub fn parse_optional_query(input: Option<&str>) -> Result<Value, String> {
+    match input {
+        Some(q) => parse_query(q),
+        None => Ok(json!({ "match_all": {} })),
+    }
+}
+
+/// Parse repeated `key=value` parameters. Values that are valid JSON (numbers,
+/// booleans, arrays...) keep their type, anything else is a string.
+pub fn parse_params(params: &[String]) -> Result<Map<String, Value>, String> {
+    params
+        .iter()
+        .map(|param| {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid parameter \"{}\": expected key=value", param))?;
+            let value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Probability for a random_sampler aggregation to keep about `wanted` of `total`
+/// documents. None when that would be more than the sampler accepts (0.5, or
+/// exactly 1): the documents are few enough to be read without sampling.
+pub fn sampler_probability(wanted: u64, total: u64) -> Option<f64> {
+    let probability = wanted.max(1) as f64 / total.max(1) as f64;
+    (probability <= 0.5).then_some(probability)
+}
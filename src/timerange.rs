@@ -0,0 +1,446 @@
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, Local, Months, NaiveDate, NaiveDateTime,
+    NaiveTime, SecondsFormat, TimeZone, Timelike, Weekday,
+};
+use chrono_tz::Tz;
+use clap::Args;
+use serde_json::{json, Value};
+
+/// Time filter options shared by commands that query documents
+#[derive(Args)]
+pub struct TimeArgs {
+    /// Time filter: documents since a duration ago or a point in time
+    /// Example: 90m, 2d, yesterday, "last monday", now-1d/d, 2024-01-31
+    #[arg(long, conflicts_with = "from")]
+    pub since: Option<String>,
+
+    /// Time filter: start time (duration ago, relative expression, date math, date or epoch millis)
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Time filter: end time, including the whole day, month or rounded unit it names
+    /// (duration ago, relative expression, date math, date or epoch millis)
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Timezone for dates without offset and day/week rounding (default: local)
+    /// Example: Europe/Paris, UTC
+    #[arg(long)]
+    pub tz: Option<String>,
+}
+
+/// A resolved, validated time range. Bounds are absolute timestamps.
+pub struct TimeRange {
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeArgs {
+    /// Parse and validate the time options locally, before any request is sent
+    pub fn resolve(&self) -> Result<TimeRange, String> {
+        match self.tz.as_deref() {
+            None => self.resolve_at(Local::now()),
+            Some(name) => {
+                let tz: Tz = name.parse().map_err(|_| {
+                    format!("Unknown timezone \"{}\" (e.g., Europe/Paris, UTC)", name)
+                })?;
+                self.resolve_at(Local::now().with_timezone(&tz))
+            }
+        }
+    }
+
+    fn resolve_at<Z: TimeZone>(&self, now: DateTime<Z>) -> Result<TimeRange, String> {
+        let from = self
+            .since
+            .as_deref()
+            .or(self.from.as_deref())
+            .map(|expr| parse_time(expr, &now, false))
+            .transpose()?;
+        // The end bound includes the whole period it names, as Elasticsearch rounds `lte`
+        let to = self
+            .to
+            .as_deref()
+            .map(|expr| parse_time(expr, &now, true))
+            .transpose()?;
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(format!(
+                    "Time range is empty: start {} is after end {}",
+                    from.to_rfc3339(),
+                    to.to_rfc3339()
+                ));
+            }
+        }
+
+        Ok(TimeRange { from, to })
+    }
+
+    /// Timezone for Elasticsearch date rounding: the --tz name, or the local zone
+    /// name so that buckets follow DST changes. The current UTC offset is the last
+    /// resort, when the local zone has no known name.
+    pub fn time_zone(&self) -> String {
+        self.tz
+            .clone()
+            .or_else(local_zone_name)
+            .unwrap_or_else(|| Local::now().format("%:z").to_string())
+    }
+}
+
+/// IANA name of the local timezone: TZ when set, as the clock follows it, else the
+/// system setting. None for zones without a name, like POSIX TZ rules.
+fn local_zone_name() -> Option<String> {
+    let name = match std::env::var("TZ") {
+        Ok(tz) if !tz.is_empty() => tz.trim_start_matches(':').to_string(),
+        _ => iana_time_zone::get_timezone().ok()?,
+    };
+    name.parse::<Tz>().is_ok().then_some(name)
+}
+
+impl TimeRange {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// Range clause on `field`, or None when no bound is set
+    pub fn filter(&self, field: &str) -> Option<Value> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut bounds = json!({});
+        if let Some(from) = self.from {
+            bounds["gte"] = json!(from.to_rfc3339_opts(SecondsFormat::Millis, false));
+        }
+        if let Some(to) = self.to {
+            bounds["lte"] = json!(to.to_rfc3339_opts(SecondsFormat::Millis, false));
+        }
+        Some(json!({ "range": { field: bounds } }))
+    }
+
+    /// Wrap `query` in a bool query filtered on this range (unchanged if no bound is set)
+    pub fn apply(&self, query: Value, field: &str) -> Value {
+        match self.filter(field) {
+            Some(range) => json!({
+                "bool": {
+                    "must": [query],
+                    "filter": [range]
+                }
+            }),
+            None => query,
+        }
+    }
+}
+
+/// Shift `time` back by a duration like "15m", "1h" or "7d"
+pub fn shift_back(
+    time: DateTime<FixedOffset>,
+    duration: &str,
+) -> Result<DateTime<FixedOffset>, String> {
+    split_duration(duration.trim())
+        .filter(|(amount, _)| *amount > 0)
+        .and_then(|(amount, unit)| shift(time, -amount, unit))
+        .ok_or_else(|| {
+            format!(
+                "Invalid duration \"{}\": expected e.g. 15m, 1h, 1d, 1w",
+                duration
+            )
+        })
+}
+
+/// `date_histogram` interval parameter. Calendar units (w, M, q, y and named
+/// units like "month") go in `calendar_interval`, the rest in `fixed_interval`.
+pub fn interval_param(interval: &str) -> Result<(&'static str, String), String> {
+    const NAMED: [&str; 7] = ["minute", "hour", "day", "week", "month", "quarter", "year"];
+    if NAMED.contains(&interval) {
+        return Ok(("calendar_interval", interval.to_string()));
+    }
+
+    let digits = interval.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (amount, unit) = interval.split_at(digits);
+    let amount: u64 = amount.parse().unwrap_or(0);
+    match unit {
+        "w" | "M" | "q" | "y" if amount == 1 => Ok(("calendar_interval", interval.to_string())),
+        "w" | "M" | "q" | "y" => Err(format!(
+            "Invalid interval \"{}\": calendar intervals (w, M, q, y) only support a quantity of 1 \
+             (e.g., 1w, 1M); use days for longer fixed periods (e.g., 14d)",
+            interval
+        )),
+        "ms" | "s" | "m" | "h" | "d" if amount > 0 => Ok(("fixed_interval", interval.to_string())),
+        _ => Err(format!(
+            "Invalid interval \"{}\": expected e.g. 30s, 5m, 1h, 1d, 1w, 1M, 1q, 1y",
+            interval
+        )),
+    }
+}
+
+/// Smallest round interval that splits `seconds` into at most `buckets` buckets
+pub fn auto_interval(seconds: i64, buckets: usize) -> String {
+    const STEPS: [(i64, &str); 16] = [
+        (1, "1s"),
+        (5, "5s"),
+        (10, "10s"),
+        (30, "30s"),
+        (60, "1m"),
+        (300, "5m"),
+        (600, "10m"),
+        (900, "15m"),
+        (1800, "30m"),
+        (3600, "1h"),
+        (3 * 3600, "3h"),
+        (6 * 3600, "6h"),
+        (12 * 3600, "12h"),
+        (86400, "1d"),
+        (7 * 86400, "1w"),
+        (30 * 86400, "1M"),
+    ];
+    let buckets = buckets.max(1) as i64;
+    let target = (seconds + buckets - 1) / buckets;
+    STEPS
+        .iter()
+        .find(|(step, _)| *step >= target)
+        .map(|(_, interval)| interval.to_string())
+        .unwrap_or_else(|| "1y".to_string())
+}
+
+fn invalid(expr: &str) -> String {
+    format!(
+        "Invalid time \"{}\": expected a duration (90m, 2d), today, yesterday, \
+         last <weekday>, date math (now-1d/d), a date (2024-01, 2024-01-31, 2024-01-31T10:00:00) \
+         or epoch milliseconds",
+        expr
+    )
+}
+
+/// Parse a single point in time relative to `now`, in the timezone of `now`.
+/// With `round_up`, a date, day name or rounded date math resolves to the last
+/// millisecond of its period instead of the first.
+fn parse_time<Z: TimeZone>(
+    expr: &str,
+    now: &DateTime<Z>,
+    round_up: bool,
+) -> Result<DateTime<FixedOffset>, String> {
+    let trimmed = expr.trim();
+    let lower = trimmed.to_lowercase();
+    let tz = now.timezone();
+    let day = |date: NaiveDate| {
+        let start = tz
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest();
+        start
+            .and_then(|t| {
+                if round_up {
+                    round_up_to(t, 'd')
+                } else {
+                    Some(t)
+                }
+            })
+            .map(|t| t.fixed_offset())
+            .ok_or_else(|| invalid(expr))
+    };
+
+    // Durations are "that long ago"
+    if let Some((amount, unit)) = split_duration(trimmed) {
+        return shift(now.clone(), -amount, unit)
+            .map(|t| t.fixed_offset())
+            .ok_or_else(|| invalid(expr));
+    }
+
+    match lower.as_str() {
+        "now" => return Ok(now.fixed_offset()),
+        "today" => return day(now.date_naive()),
+        "yesterday" => return day(now.date_naive() - Days::new(1)),
+        _ => {}
+    }
+
+    if let Some(weekday) = lower.strip_prefix("last ") {
+        let weekday: Weekday = weekday.trim().parse().map_err(|_| invalid(expr))?;
+        let today = now.date_naive();
+        let mut back =
+            (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        if back == 0 {
+            back = 7;
+        }
+        return day(today - Days::new(back as u64));
+    }
+
+    if let Some(math) = trimmed.strip_prefix("now") {
+        return parse_date_math(math, now.clone(), round_up)
+            .map(|t| t.fixed_offset())
+            .ok_or_else(|| invalid(expr));
+    }
+
+    // Absolute timestamps with an explicit offset
+    if let Ok(t) = DateTime::parse_from_rfc3339(trimmed) {
+        return Ok(t);
+    }
+
+    // Absolute dates without offset are in the selected timezone. The unit is the
+    // precision of the format, that an end bound is rounded up to.
+    const FORMATS: [(&str, Option<char>); 5] = [
+        ("%Y-%m-%dT%H:%M:%S%.f", None),
+        ("%Y-%m-%dT%H:%M:%S", None),
+        ("%Y-%m-%dT%H:%M", Some('m')),
+        ("%Y-%m-%d %H:%M:%S", None),
+        ("%Y-%m-%d %H:%M", Some('m')),
+    ];
+    let digits = !trimmed.is_empty() && trimmed.bytes().all(|b| b.is_ascii_digit());
+    let parsed = FORMATS
+        .iter()
+        .find_map(|(f, unit)| Some((NaiveDateTime::parse_from_str(trimmed, f).ok()?, *unit)))
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d").ok()?;
+            Some((date.and_time(NaiveTime::MIN), Some('d')))
+        })
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(&format!("{}-01", trimmed), "%Y-%m-%d").ok()?;
+            Some((date.and_time(NaiveTime::MIN), Some('M')))
+        })
+        .or_else(|| {
+            // A bare year, as in Elasticsearch's strict_date_optional_time
+            let year = trimmed
+                .parse()
+                .ok()
+                .filter(|_| digits && trimmed.len() == 4)?;
+            Some((
+                NaiveDate::from_ymd_opt(year, 1, 1)?.and_time(NaiveTime::MIN),
+                Some('y'),
+            ))
+        });
+
+    let Some((naive, unit)) = parsed else {
+        // Epoch milliseconds, as Elasticsearch accepts them
+        return trimmed
+            .parse()
+            .ok()
+            .filter(|_| digits)
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|t| t.with_timezone(&tz).fixed_offset())
+            .ok_or_else(|| invalid(expr));
+    };
+
+    let time = tz
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("Invalid time \"{}\": does not exist in this timezone", expr))?;
+    match unit {
+        Some(unit) if round_up => round_up_to(time, unit)
+            .map(|t| t.fixed_offset())
+            .ok_or_else(|| invalid(expr)),
+        _ => Ok(time.fixed_offset()),
+    }
+}
+
+/// Split "90m" into (90, 'm'). Units: s, m, h, d, w, M (months), y.
+fn split_duration(expr: &str) -> Option<(i64, char)> {
+    let unit = expr.chars().last()?;
+    let amount = &expr[..expr.len() - unit.len_utf8()];
+    if amount.is_empty() || !amount.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if !"smhdwMy".contains(unit) {
+        return None;
+    }
+    Some((amount.parse().ok()?, unit))
+}
+
+/// Elasticsearch-style date math after "now", e.g. "-1d/d" or "+2h". With
+/// `round_up`, rounding goes to the end of the unit instead of its start.
+fn parse_date_math<Z: TimeZone>(
+    math: &str,
+    mut time: DateTime<Z>,
+    round_up: bool,
+) -> Option<DateTime<Z>> {
+    let mut rest = math;
+    while !rest.is_empty() {
+        let op = rest.chars().next()?;
+        rest = &rest[op.len_utf8()..];
+        match op {
+            '+' | '-' => {
+                let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+                let amount: i64 = if digits == 0 {
+                    1
+                } else {
+                    rest[..digits].parse().ok()?
+                };
+                let unit = rest[digits..].chars().next()?;
+                rest = &rest[digits + unit.len_utf8()..];
+                let amount = if op == '-' { -amount } else { amount };
+                time = shift(time, amount, unit)?;
+            }
+            '/' => {
+                let unit = rest.chars().next()?;
+                rest = &rest[unit.len_utf8()..];
+                time = if round_up {
+                    round_up_to(time, unit)?
+                } else {
+                    round_down(time, unit)?
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(time)
+}
+
+fn shift<Z: TimeZone>(time: DateTime<Z>, amount: i64, unit: char) -> Option<DateTime<Z>> {
+    match unit {
+        's' => time.checked_add_signed(Duration::try_seconds(amount)?),
+        'm' => time.checked_add_signed(Duration::try_minutes(amount)?),
+        'h' | 'H' => time.checked_add_signed(Duration::try_hours(amount)?),
+        'd' => shift_calendar(time, |n| shift_days(n, amount)),
+        'w' => shift_calendar(time, |n| shift_days(n, amount.checked_mul(7)?)),
+        'M' => shift_calendar(time, |n| shift_months(n, amount)),
+        'y' => shift_calendar(time, |n| shift_months(n, amount.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+fn shift_days(naive: NaiveDateTime, amount: i64) -> Option<NaiveDateTime> {
+    let days = Days::new(amount.unsigned_abs());
+    if amount < 0 {
+        naive.checked_sub_days(days)
+    } else {
+        naive.checked_add_days(days)
+    }
+}
+
+fn shift_months(naive: NaiveDateTime, amount: i64) -> Option<NaiveDateTime> {
+    let months = Months::new(u32::try_from(amount.unsigned_abs()).ok()?);
+    if amount < 0 {
+        naive.checked_sub_months(months)
+    } else {
+        naive.checked_add_months(months)
+    }
+}
+
+/// Apply a calendar operation on wall-clock time, keeping the timezone
+fn shift_calendar<Z: TimeZone>(
+    time: DateTime<Z>,
+    f: impl Fn(NaiveDateTime) -> Option<NaiveDateTime>,
+) -> Option<DateTime<Z>> {
+    let naive = f(time.naive_local())?;
+    time.timezone().from_local_datetime(&naive).earliest()
+}
+
+fn round_down<Z: TimeZone>(time: DateTime<Z>, unit: char) -> Option<DateTime<Z>> {
+    let date = time.date_naive();
+    let naive = match unit {
+        's' => time.naive_local().with_nanosecond(0)?,
+        'm' => time.naive_local().with_nanosecond(0)?.with_second(0)?,
+        'h' | 'H' => date.and_hms_opt(time.hour(), 0, 0)?,
+        'd' => date.and_time(NaiveTime::MIN),
+        'w' => (date - Days::new(date.weekday().num_days_from_monday() as u64))
+            .and_time(NaiveTime::MIN),
+        'M' => date.with_day(1)?.and_time(NaiveTime::MIN),
+        'y' => NaiveDate::from_ymd_opt(date.year(), 1, 1)?.and_time(NaiveTime::MIN),
+        _ => return None,
+    };
+    time.timezone().from_local_datetime(&naive).earliest()
+}
+
+/// Last millisecond of the `unit` period containing `time`
+fn round_up_to<Z: TimeZone>(time: DateTime<Z>, unit: char) -> Option<DateTime<Z>> {
+    let next = shift(round_down(time, unit)?, 1, unit)?;
+    next.checked_sub_signed(Duration::try_milliseconds(1)?)
+}